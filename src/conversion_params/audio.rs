@@ -1,10 +1,18 @@
-use crate::{logger, media_file::MediaFileTrack};
+use crate::{
+    logger,
+    media_file::{Codec, MediaFileTrack},
+};
 
 use core::fmt;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use super::params_trait::ConversionParams;
 
+/// The bitrate allocated per audio channel when automatically selecting a bitrate, in kilobits per second.
+const AUTO_BITRATE_PER_CHANNEL: u32 = 64;
+/// The maximum bitrate that will be automatically selected, in kilobits per second.
+const AUTO_BITRATE_MAX: u32 = 512;
+
 /// Features supported by the audio codec.
 enum CodecFeatures {
     /// Compression.
@@ -14,7 +22,7 @@ enum CodecFeatures {
 }
 
 /// Variable bitrate options applicable to the Opus codec.
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum OpusVbrOptions {
     /// Disable variable bitrate, enabling constant bitrate.
     Off,
@@ -34,10 +42,99 @@ impl fmt::Display for OpusVbrOptions {
     }
 }
 
-#[derive(Clone, Deserialize)]
+/// The downmix options applicable to audio tracks.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum DownmixMode {
+    /// Downmix the track into a single channel.
+    Mono,
+    /// Downmix the track into two channels.
+    Stereo,
+}
+
+impl DownmixMode {
+    /// Get the number of output channels for the downmix.
+    fn channels(&self) -> u32 {
+        match self {
+            DownmixMode::Mono => 1,
+            DownmixMode::Stereo => 2,
+        }
+    }
+}
+
+/// How the number of channels of an audio track should be handled during the conversion.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum ChannelHandling {
+    /// Keep the same number of channels as the source track.
+    Source,
+    /// Always use the specified number of channels, regardless of the source track.
+    Force(u32),
+    /// Reduce the number of channels to the specified number, if the source track has more channels.
+    /// If the number of channels in the source track is unknown, the number of channels will always be set.
+    DownmixTo(u32),
+}
+
+/// EBU R128 loudness normalization options. Any unspecified values will use the FFMPEG defaults.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LoudnormParams {
+    /// The target integrated loudness, in LUFS. Must be in the range of -70 to -5.
+    pub integrated: Option<f32>,
+    /// The maximum true peak, in dBTP. Must be in the range of -9 to 0.
+    pub true_peak: Option<f32>,
+    /// The target loudness range, in LU. Must be in the range of 1 to 50.
+    pub loudness_range: Option<f32>,
+}
+
+impl LoudnormParams {
+    /// Validate the specified loudness normalization parameters.
+    fn validate(&self) -> bool {
+        let checks = [
+            ("integrated loudness", self.integrated, -70.0, -5.0),
+            ("true peak", self.true_peak, -9.0, 0.0),
+            ("loudness range", self.loudness_range, 1.0, 50.0),
+        ];
+
+        let mut valid = true;
+        for (name, value, min, max) in checks {
+            if let Some(v) = value {
+                if !(min..=max).contains(&v) {
+                    logger::log(
+                        format!("The normalization {name} must be between {min} and {max}, but {v} was specified."),
+                        true,
+                    );
+                    valid = false;
+                }
+            }
+        }
+
+        valid
+    }
+}
+
+impl fmt::Display for LoudnormParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut options = Vec::new();
+
+        if let Some(i) = self.integrated {
+            options.push(format!("I={i}"));
+        }
+        if let Some(tp) = self.true_peak {
+            options.push(format!("TP={tp}"));
+        }
+        if let Some(lra) = self.loudness_range {
+            options.push(format!("LRA={lra}"));
+        }
+
+        if options.is_empty() {
+            write!(f, "loudnorm")
+        } else {
+            write!(f, "loudnorm={}", options.join(":"))
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub enum VbrOptions {
     Opus(OpusVbrOptions),
-    // TODO: validate that this is in the range of 1 to 5.
     AacLibfdk(u8),
 }
 
@@ -50,16 +147,18 @@ impl fmt::Display for VbrOptions {
     }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum AudioCodec {
     Aac,
     AacLibfdk,
     Ac3,
     Flac,
+    Mp2,
     Mp3Lame,
     Mp3Shine,
     Opus,
     Vorbis,
+    WavPack,
 }
 
 impl fmt::Display for AudioCodec {
@@ -69,22 +168,32 @@ impl fmt::Display for AudioCodec {
             AudioCodec::AacLibfdk => write!(f, "libfdk_aac"),
             AudioCodec::Ac3 => write!(f, "ac3"),
             AudioCodec::Flac => write!(f, "flac"),
+            AudioCodec::Mp2 => write!(f, "libtwolame"),
             AudioCodec::Mp3Lame => write!(f, "libmp3lame"),
             AudioCodec::Mp3Shine => write!(f, "libshine"),
             AudioCodec::Opus => write!(f, "libopus"),
             AudioCodec::Vorbis => write!(f, "libvorbis"),
+            AudioCodec::WavPack => write!(f, "wavpack"),
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct AudioConvertParams {
     /// The audio codec to be used for the conversion.
     pub codec: Option<AudioCodec>,
-    /// The number of channels to be used for the conversion. If None, the number will be the same as the source.
-    pub channels: Option<u32>,
+    /// The source codecs of any tracks that should be left unconverted.
+    pub skip_codecs: Option<Vec<Codec>>,
+    /// How the number of channels should be handled during the conversion. If None, the number will be the same as the source.
+    pub channels: Option<ChannelHandling>,
+    /// The downmix to be applied to the track. This will take precedence over the number of channels, if specified.
+    pub downmix: Option<DownmixMode>,
     /// The bitrate for the audio conversion, in kilobits per second.
     pub bitrate: Option<u32>,
+    /// Should a bitrate be automatically selected based on the number of output channels, if no bitrate is specified?
+    pub auto_bitrate: Option<bool>,
+    /// The sample rate for the audio conversion, in hertz. If None, the sample rate will be the same as the source.
+    pub sample_rate: Option<u32>,
     /// The variable bitrate (VBR) options to be used.
     /// Only applicable when the codec is [`AudioCodec::Opus`] or [`AudioCodec::AacLibfdk`].
     pub vbr: Option<VbrOptions>,
@@ -97,6 +206,18 @@ pub struct AudioConvertParams {
     pub volume_adjustment: Option<String>,
     /// Any custom filers to be applied.
     pub filters: Option<String>,
+    /// Should EBU R128 loudness normalization be applied?
+    pub normalize: Option<LoudnormParams>,
+    /// Should the audio tracks within a media file be converted concurrently?
+    pub parallel_tracks: Option<bool>,
+    /// The maximum number of audio tracks to be converted at once, when converting concurrently.
+    /// If unspecified, all of the audio tracks will be converted at once.
+    pub max_parallel_tracks: Option<usize>,
+    /// Should tracks be converted even if they already match the conversion parameters?
+    pub force_reencode: Option<bool>,
+    /// Any extra arguments to be passed to FFMPEG, immediately before the output file path.
+    /// These are passed verbatim and are not sanitized or validated in any way.
+    pub extra_ffmpeg_args: Option<Vec<String>>,
 }
 
 impl ConversionParams for AudioConvertParams {
@@ -111,22 +232,97 @@ impl ConversionParams for AudioConvertParams {
         let mut valid = true;
 
         match codec {
-            // Validate the Opus audio conversion parameters.
-            AudioCodec::Opus => {
+            AudioCodec::AacLibfdk => {
                 if let Some(v) = &self.vbr {
-                    valid &= matches!(v, VbrOptions::Opus(_));
+                    if !matches!(v, VbrOptions::AacLibfdk(1..=5)) {
+                        logger::log(
+                            format!(
+                                "The VBR quality for the codec {codec} must be between 1 and 5."
+                            ),
+                            true,
+                        );
+                        valid = false;
+                    }
                 }
-
-                if let Some(c) = &self.compression_level {
-                    valid &= *c <= 10;
+            }
+            AudioCodec::Flac => {
+                valid &= AudioConvertParams::check_range(
+                    codec,
+                    "compression level",
+                    self.compression_level.map(u32::from),
+                    0,
+                    8,
+                );
+            }
+            AudioCodec::Opus => {
+                if let Some(v) = &self.vbr {
+                    if !matches!(v, VbrOptions::Opus(_)) {
+                        logger::log(
+                            format!(
+                                "The VBR options specified are not valid for the codec {codec}."
+                            ),
+                            true,
+                        );
+                        valid = false;
+                    }
                 }
 
-                valid
+                valid &= AudioConvertParams::check_range(
+                    codec,
+                    "compression level",
+                    self.compression_level.map(u32::from),
+                    0,
+                    10,
+                );
+            }
+            AudioCodec::WavPack => {
+                valid &= AudioConvertParams::check_range(
+                    codec,
+                    "compression level",
+                    self.compression_level.map(u32::from),
+                    0,
+                    8,
+                );
             }
-            _ => {
-                todo!("Handle these cases.");
+            AudioCodec::Aac
+            | AudioCodec::Ac3
+            | AudioCodec::Mp2
+            | AudioCodec::Mp3Lame
+            | AudioCodec::Mp3Shine
+            | AudioCodec::Vorbis => {}
+        }
+
+        // Not all codecs support arbitrary bitrates.
+        if let Some((min, max)) = codec.bitrate_range() {
+            valid &= AudioConvertParams::check_range(codec, "bitrate", self.bitrate, min, max);
+        }
+
+        // A track can't be converted to have no channels.
+        if let Some(ChannelHandling::Force(0) | ChannelHandling::DownmixTo(0)) = &self.channels {
+            logger::log(
+                "The number of audio channels must be greater than zero.",
+                true,
+            );
+            valid = false;
+        }
+
+        // Validate the loudness normalization parameters.
+        if let Some(normalize) = &self.normalize {
+            valid &= normalize.validate();
+        }
+
+        // Not all codecs support arbitrary sample rates.
+        if let (Some(rate), Some(rates)) = (self.sample_rate, codec.supported_sample_rates()) {
+            if !rates.contains(&rate) {
+                logger::log(
+                    format!("The sample rate {rate} Hz is not supported by the codec {codec}."),
+                    true,
+                );
+                valid = false;
             }
         }
+
+        valid
     }
 
     fn as_ffmpeg_argument_list(
@@ -167,25 +363,69 @@ impl ConversionParams for AudioConvertParams {
         args.push("-c:a".to_string());
         args.push(format!("{codec}"));
 
-        // Bitrate.
-        if let Some(bitrate) = self.bitrate {
+        // The number of output audio channels, if it should be changed.
+        let channels = self.target_channels(track);
+
+        // Bitrate. If none is specified then one may be selected based on the number of output channels.
+        // A source track that reports no channels has an unknown number of channels, in which case
+        // the encoder will select the bitrate.
+        let bitrate = self.bitrate.or_else(|| {
+            let out_channels = channels.unwrap_or(track.channels);
+            if self.auto_bitrate.unwrap_or(false) && codec.is_lossy() && out_channels > 0 {
+                Some(AudioConvertParams::auto_bitrate(codec, out_channels))
+            } else {
+                None
+            }
+        });
+        if let Some(bitrate) = bitrate {
             args.push("-b:a".to_string());
             args.push(format!("{bitrate}k"));
         }
 
         // Filters. These are simply treated as strings since the format is too complex to be
-        // easily represented by other means.
-        if let Some(filters) = &self.filters {
-            // Add any manually specified filters first.
-            let mut filters_sub = filters.clone();
+        // easily represented by other means. All of the filters are composed into a single
+        // filtergraph, as FFMPEG will only honour the last filter argument.
+        let mut filters = Vec::new();
+
+        // Add any manually specified filters first. Any stray separators are removed so
+        // that the combined filtergraph does not contain any empty filters.
+        if let Some(f) = &self.filters {
+            let f = f.trim().trim_matches([',', ';']);
+            if !f.is_empty() {
+                filters.push(f.to_string());
+            }
+        }
 
-            // Volume adjustment, if specified.
-            if let Some(vol) = &self.volume_adjustment {
-                filters_sub.push_str(&format!("volume={vol};"));
+        // Downmix filter, if specified. A stereo downmix of a multichannel track
+        // will be matrix encoded to give a Dolby Pro Logic II compatible Lt/Rt fold-down.
+        // A mono downmix of a stereo track averages the two channels, while a multichannel
+        // track is folded down using the standard FFMPEG matrix, so that the center channel is kept.
+        match &self.downmix {
+            Some(DownmixMode::Stereo) if track.channels > 2 => {
+                filters.push("aresample=ocl=stereo:matrix_encoding=dplii".to_string());
+            }
+            Some(DownmixMode::Mono) if track.channels == 2 => {
+                filters.push("pan=mono|c0=0.5*c0+0.5*c1".to_string());
+            }
+            Some(DownmixMode::Mono) if track.channels > 2 => {
+                filters.push("aresample=ocl=mono".to_string());
             }
+            _ => {}
+        }
+
+        // Volume adjustment, if specified.
+        if let Some(vol) = &self.volume_adjustment {
+            filters.push(format!("volume={vol}"));
+        }
+
+        // Loudness normalization, if specified.
+        if let Some(normalize) = &self.normalize {
+            filters.push(format!("{normalize}"));
+        }
 
-            args.push("-af".to_string());
-            args.push(filters_sub);
+        if !filters.is_empty() {
+            args.push("-filter:a".to_string());
+            args.push(filters.join(","));
         }
 
         // Variable bitrate (VBR).
@@ -214,13 +454,24 @@ impl ConversionParams for AudioConvertParams {
         }
 
         // The number of audio channels.
-        if let Some(channels) = self.channels {
-            if track.channels != channels {
-                args.push("-ac".to_string());
-                args.push(channels.to_string());
+        if let Some(channels) = channels {
+            args.push("-ac".to_string());
+            args.push(channels.to_string());
+        }
+
+        // The audio sample rate.
+        if let Some(sample_rate) = self.sample_rate {
+            if track.sample_rate != sample_rate {
+                args.push("-ar".to_string());
+                args.push(sample_rate.to_string());
             }
         }
 
+        // Any extra arguments.
+        if let Some(extra) = &self.extra_ffmpeg_args {
+            args.extend(extra.iter().cloned());
+        }
+
         // The output file path should always go last.
         args.push(file_out.to_string());
 
@@ -228,6 +479,122 @@ impl ConversionParams for AudioConvertParams {
     }
 }
 
+impl AudioConvertParams {
+    /// Check whether a track already matches the conversion parameters, such that converting it would
+    /// only result in a loss of quality.
+    ///
+    /// # Arguments
+    ///
+    /// * `track` - A reference to the media file track instance.
+    ///
+    /// # Returns
+    ///
+    /// True if the track already matches the conversion parameters, false otherwise.
+    pub fn matches_source(&self, track: &MediaFileTrack) -> bool {
+        let codec = match &self.codec {
+            Some(c) => c,
+            None => return false,
+        };
+
+        if Codec::from(codec.clone()) != track.codec {
+            return false;
+        }
+
+        // Any filters would alter the track, and so the track must be converted.
+        if self.filters.is_some() || self.volume_adjustment.is_some() || self.normalize.is_some() {
+            return false;
+        }
+
+        // Allow for a small deviation in the bitrate, as the reported bitrate of a variable bitrate
+        // track will rarely be an exact match.
+        if let Some(bitrate) = self.bitrate {
+            let source = track.bitrate / 1000;
+            if source == 0 || source.abs_diff(bitrate) > bitrate / 20 {
+                return false;
+            }
+        }
+
+        if self
+            .target_channels(track)
+            .is_some_and(|c| c != track.channels)
+        {
+            return false;
+        }
+
+        self.sample_rate.is_none_or(|r| r == track.sample_rate)
+    }
+
+    /// Get the number of channels that the converted track should have, if it should be changed.
+    /// A downmix will take precedence over the channel handling, if both are specified.
+    /// A source track that reports no channels has an unknown number of channels, so any
+    /// requested number of channels will always be applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `track` - A reference to the media file track instance.
+    ///
+    /// # Returns
+    ///
+    /// The number of output channels, or None if the number of channels should not be changed.
+    fn target_channels(&self, track: &MediaFileTrack) -> Option<u32> {
+        if let Some(d) = &self.downmix {
+            return (track.channels != d.channels()).then(|| d.channels());
+        }
+
+        match self.channels.as_ref()? {
+            ChannelHandling::Source => None,
+            ChannelHandling::Force(n) => Some(*n),
+            ChannelHandling::DownmixTo(n) => {
+                (track.channels == 0 || track.channels > *n).then_some(*n)
+            }
+        }
+    }
+
+    /// Select a bitrate based on the number of audio channels, within the range supported by the codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The codec to be used for the conversion.
+    /// * `channels` - The number of output audio channels.
+    ///
+    /// # Returns
+    ///
+    /// The selected bitrate, in kilobits per second.
+    fn auto_bitrate(codec: &AudioCodec, channels: u32) -> u32 {
+        let bitrate = (channels * AUTO_BITRATE_PER_CHANNEL).min(AUTO_BITRATE_MAX);
+        match codec.bitrate_range() {
+            Some((min, max)) => bitrate.clamp(min, max),
+            None => bitrate,
+        }
+    }
+
+    /// Check that a parameter, if specified, falls within the range supported by the codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The codec to be used for the conversion.
+    /// * `name` - The name of the parameter being checked.
+    /// * `value` - The value of the parameter, if specified.
+    /// * `min` - The minimum permitted value, inclusive.
+    /// * `max` - The maximum permitted value, inclusive.
+    ///
+    /// # Returns
+    ///
+    /// True if the parameter was unspecified or within the range, false otherwise.
+    fn check_range(codec: &AudioCodec, name: &str, value: Option<u32>, min: u32, max: u32) -> bool {
+        match value {
+            Some(v) if v < min || v > max => {
+                logger::log(
+                    format!("The {name} for the codec {codec} must be between {min} and {max}, but {v} was specified."),
+                    true,
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
 impl AudioCodec {
     /// Indicates whether the codec supports a specific codec feature.
     ///
@@ -238,9 +605,283 @@ impl AudioCodec {
         match &feature {
             CodecFeatures::Compression => matches!(
                 self,
-                AudioCodec::Flac | AudioCodec::Mp3Lame | AudioCodec::Opus
+                AudioCodec::Flac | AudioCodec::Mp3Lame | AudioCodec::Opus | AudioCodec::WavPack
             ),
             CodecFeatures::Vbr => matches!(self, AudioCodec::AacLibfdk | AudioCodec::Opus),
         }
     }
+
+    /// Indicates whether the codec is lossy, and so may be encoded at a specific bitrate.
+    fn is_lossy(&self) -> bool {
+        !matches!(self, AudioCodec::Flac | AudioCodec::WavPack)
+    }
+
+    /// Get the range of bitrates supported by the codec.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the minimum and maximum bitrates, in kilobits per second, or None if the codec is not restricted to a range.
+    fn bitrate_range(&self) -> Option<(u32, u32)> {
+        match self {
+            AudioCodec::Ac3 => Some((32, 640)),
+            AudioCodec::Mp2 => Some((32, 384)),
+            AudioCodec::Mp3Lame => Some((8, 320)),
+            AudioCodec::Mp3Shine => Some((32, 320)),
+            _ => None,
+        }
+    }
+
+    /// Get the list of sample rates supported by the codec.
+    ///
+    /// # Returns
+    ///
+    /// A slice of the supported sample rates, in hertz, or None if the codec is not restricted to a fixed set.
+    fn supported_sample_rates(&self) -> Option<&'static [u32]> {
+        match self {
+            AudioCodec::Ac3 => Some(&[32000, 44100, 48000]),
+            AudioCodec::Mp2 => Some(&[16000, 22050, 24000, 32000, 44100, 48000]),
+            AudioCodec::Mp3Lame => {
+                Some(&[8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000])
+            }
+            AudioCodec::Mp3Shine => Some(&[32000, 44100, 48000]),
+            AudioCodec::Opus => Some(&[8000, 12000, 16000, 24000, 48000]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a set of [`AudioConvertParams`] from a JSON object.
+    fn params_from_json(json: serde_json::Value) -> AudioConvertParams {
+        serde_json::from_value(json).unwrap()
+    }
+
+    /// Create an audio track with the given number of channels.
+    fn audio_track(channels: u32) -> MediaFileTrack {
+        serde_json::from_value(serde_json::json!({
+            "@type": "Audio",
+            "Channels": channels.to_string(),
+            "SamplingRate": "48000",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn bitrates_are_validated_per_codec() {
+        for (codec, min, max) in [
+            ("Ac3", 32, 640),
+            ("Mp2", 32, 384),
+            ("Mp3Lame", 8, 320),
+            ("Mp3Shine", 32, 320),
+        ] {
+            for bitrate in [min, max] {
+                let params =
+                    params_from_json(serde_json::json!({ "codec": codec, "bitrate": bitrate }));
+                assert!(params.validate(), "{codec} at {bitrate}k should be valid");
+            }
+
+            for bitrate in [min - 1, max + 1] {
+                let params =
+                    params_from_json(serde_json::json!({ "codec": codec, "bitrate": bitrate }));
+                assert!(
+                    !params.validate(),
+                    "{codec} at {bitrate}k should be invalid"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unrestricted_bitrates_are_valid() {
+        for codec in ["Aac", "Opus", "Vorbis"] {
+            let params = params_from_json(serde_json::json!({ "codec": codec, "bitrate": 1024 }));
+            assert!(params.validate(), "{codec}");
+        }
+    }
+
+    #[test]
+    fn zero_channels_are_invalid_for_every_codec() {
+        for codec in ["Aac", "Ac3", "Flac", "Opus"] {
+            for channels in [
+                serde_json::json!({ "Force": 0 }),
+                serde_json::json!({ "DownmixTo": 0 }),
+            ] {
+                let params =
+                    params_from_json(serde_json::json!({ "codec": codec, "channels": channels }));
+                assert!(!params.validate(), "{codec} with {channels}");
+            }
+
+            for channels in [
+                serde_json::json!("Source"),
+                serde_json::json!({ "Force": 2 }),
+                serde_json::json!({ "DownmixTo": 6 }),
+            ] {
+                let params =
+                    params_from_json(serde_json::json!({ "codec": codec, "channels": channels }));
+                assert!(params.validate(), "{codec} with {channels}");
+            }
+        }
+    }
+
+    #[test]
+    fn check_range_is_inclusive() {
+        let codec = AudioCodec::Ac3;
+        assert!(AudioConvertParams::check_range(
+            &codec, "bitrate", None, 32, 640
+        ));
+        assert!(AudioConvertParams::check_range(
+            &codec,
+            "bitrate",
+            Some(32),
+            32,
+            640
+        ));
+        assert!(AudioConvertParams::check_range(
+            &codec,
+            "bitrate",
+            Some(640),
+            32,
+            640
+        ));
+        assert!(!AudioConvertParams::check_range(
+            &codec,
+            "bitrate",
+            Some(31),
+            32,
+            640
+        ));
+        assert!(!AudioConvertParams::check_range(
+            &codec,
+            "bitrate",
+            Some(641),
+            32,
+            640
+        ));
+    }
+
+    #[test]
+    fn downmix_and_loudnorm_share_one_filtergraph() {
+        let params = params_from_json(serde_json::json!({
+            "codec": "Opus",
+            "downmix": "Stereo",
+            "normalize": { "integrated": -16.0 },
+        }));
+
+        let args = params
+            .as_ffmpeg_argument_list(&audio_track(6), "in.mka", "out.mka")
+            .unwrap();
+        let filters: Vec<_> = args
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| *a == "-filter:a")
+            .map(|(i, _)| &args[i + 1])
+            .collect();
+
+        assert_eq!(
+            filters,
+            ["aresample=ocl=stereo:matrix_encoding=dplii,loudnorm=I=-16"]
+        );
+    }
+
+    #[test]
+    fn downmixes_change_the_channel_layout() {
+        let filter = |downmix: &str, channels: u32| {
+            let params =
+                params_from_json(serde_json::json!({ "codec": "Opus", "downmix": downmix }));
+            let args = params
+                .as_ffmpeg_argument_list(&audio_track(channels), "in.mka", "out.mka")
+                .unwrap();
+            args.iter()
+                .position(|a| a == "-filter:a")
+                .map(|i| args[i + 1].clone())
+        };
+
+        assert_eq!(
+            filter("Stereo", 6).as_deref(),
+            Some("aresample=ocl=stereo:matrix_encoding=dplii")
+        );
+        assert_eq!(filter("Stereo", 2), None);
+        assert_eq!(
+            filter("Mono", 2).as_deref(),
+            Some("pan=mono|c0=0.5*c0+0.5*c1")
+        );
+        assert_eq!(filter("Mono", 6).as_deref(), Some("aresample=ocl=mono"));
+        assert_eq!(filter("Mono", 1), None);
+    }
+
+    #[test]
+    fn unknown_channel_counts_apply_any_requested_channels() {
+        let track = audio_track(0);
+        for (channels, expected) in [
+            (serde_json::json!("Source"), None),
+            (serde_json::json!({ "Force": 2 }), Some(2)),
+            (serde_json::json!({ "DownmixTo": 2 }), Some(2)),
+        ] {
+            let params =
+                params_from_json(serde_json::json!({ "codec": "Opus", "channels": channels }));
+            assert_eq!(params.target_channels(&track), expected, "{channels}");
+        }
+
+        let params = params_from_json(serde_json::json!({ "codec": "Opus", "downmix": "Mono" }));
+        assert_eq!(params.target_channels(&track), Some(1));
+    }
+
+    #[test]
+    fn unknown_channel_counts_leave_the_bitrate_to_the_encoder() {
+        let params = params_from_json(serde_json::json!({ "codec": "Opus", "auto_bitrate": true }));
+        let args = params
+            .as_ffmpeg_argument_list(&audio_track(0), "in.mka", "out.mka")
+            .unwrap();
+        assert!(!args.iter().any(|a| a == "-b:a"));
+
+        // Once the number of output channels is known, a bitrate can be selected.
+        let params = params_from_json(serde_json::json!({
+            "codec": "Opus",
+            "auto_bitrate": true,
+            "channels": { "DownmixTo": 2 },
+        }));
+        let args = params
+            .as_ffmpeg_argument_list(&audio_track(0), "in.mka", "out.mka")
+            .unwrap();
+        let index = args.iter().position(|a| a == "-b:a").unwrap();
+        assert_eq!(args[index + 1], "128k");
+    }
+
+    #[test]
+    fn auto_bitrate_is_capped() {
+        let codec = AudioCodec::Opus;
+        assert_eq!(AudioConvertParams::auto_bitrate(&codec, 0), 0);
+        assert_eq!(AudioConvertParams::auto_bitrate(&codec, 2), 128);
+        assert_eq!(AudioConvertParams::auto_bitrate(&codec, 6), 384);
+        assert_eq!(
+            AudioConvertParams::auto_bitrate(&codec, 16),
+            AUTO_BITRATE_MAX
+        );
+    }
+
+    #[test]
+    fn auto_bitrate_is_within_the_codec_range() {
+        for (codec, channels) in [
+            (AudioCodec::Ac3, 16),
+            (AudioCodec::Mp2, 8),
+            (AudioCodec::Mp3Lame, 6),
+            (AudioCodec::Mp3Shine, 6),
+            (AudioCodec::Mp3Shine, 0),
+        ] {
+            let (min, max) = codec.bitrate_range().unwrap();
+            let bitrate = AudioConvertParams::auto_bitrate(&codec, channels);
+            assert!(
+                (min..=max).contains(&bitrate),
+                "{codec} at {channels} channels: {bitrate}k"
+            );
+        }
+
+        assert_eq!(
+            AudioConvertParams::auto_bitrate(&AudioCodec::Mp3Lame, 6),
+            320
+        );
+    }
 }
@@ -1,12 +1,14 @@
+use crate::media_file::{Codec, MediaFileTrack, TrackType};
+
 use regex::Regex;
 use serde::{Deserialize, Deserializer};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use super::{
     audio::AudioConvertParams, subtitle::SubtitleConvertParams, video::VideoConvertParams,
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct UnifiedParams {
     /// Parameters related to the audio tracks.
     pub audio_tracks: UnifiedAudioParams,
@@ -26,10 +28,12 @@ pub struct UnifiedParams {
     pub misc: MiscParams,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct TrackParams {
     /// The ID of the track to which these parameters should be applied.
     pub id: usize,
+    /// The name of the track. An empty name will clear any existing name.
+    pub name: Option<String>,
     /// Should the track be set as default?
     pub default: Option<bool>,
     /// Should the track be set as enabled? If unset, the track will be enabled by default.
@@ -48,9 +52,13 @@ pub struct TrackParams {
     pub commentary: Option<bool>,
     /// Should the delay be overridden for this track?
     pub delay_override: Option<i32>,
+    /// The compression method to be used by MkvMerge for this track, such as none or zlib.
+    pub compression: Option<String>,
+    /// The audio conversion parameters for this track, overriding the global audio conversion parameters.
+    pub audio_conversion: Option<AudioConvertParams>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct AttachmentParams {
     /// Should attachments be imported from the original file?
     /// Any existing attachments will be exported and included in the final file.
@@ -66,21 +74,108 @@ pub struct AttachmentParams {
     /// An empty list will indicate that all files should be included.
     #[serde(deserialize_with = "array_to_lowercase_string_vec")]
     pub import_folder_extensions: Option<Vec<String>>,
+    /// Should attachments be renamed if an attachment with the same name has already been added?
+    /// A numeric suffix will be appended to the name of any such attachment.
+    pub rename_duplicates: Option<bool>,
+    /// The path to an image that should be attached as the cover art of the media file.
+    /// The image must be a JPEG or PNG file.
+    pub cover_image: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ChapterParams {
     /// Should chapters be imported from the original file?
     /// Any existing chapters will be exported and included in the final file.
     pub import_from_original: bool,
+    /// The path to a chapters file that should be imported.
+    /// If specified, this will take precedence over any other chapter parameters.
+    pub import_from_file: Option<String>,
     /// Should chapters be created, if no chapters are present?
     pub create_if_not_present: bool,
     /// The interval for creating chapters, must be in the following format: HH:MM:SS.nnnnnnnnn.
     /// If `None` is specified then chapters will be created at 5 minute intervals.
     pub create_interval: Option<String>,
+    /// The template for the names of any created chapters, as used by MkvMerge.
+    /// If `None` is specified then the chapters will be named "Chapter <NUM:2>".
+    pub create_name_template: Option<String>,
+    /// The language of the chapters.
+    /// If `None` is specified then the chapters will be set as English.
+    pub language: Option<String>,
+    /// The number of milliseconds by which any imported chapters should be shifted.
+    /// Negative values will move the chapters earlier, with the times being clamped at zero.
+    pub shift_ms: Option<i32>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum Container {
+    /// A Matroska container.
+    #[default]
+    #[serde(rename = "mkv")]
+    Mkv,
+    /// A WebM container, a subset of Matroska.
+    #[serde(rename = "webm")]
+    Webm,
+}
+
+impl Container {
+    /// Get the file extension associated with the container.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mkv => "mkv",
+            Container::Webm => "webm",
+        }
+    }
+
+    /// Check whether a codec may be stored within the container.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The [`Codec`] to be checked.
+    pub fn supports_codec(&self, codec: &Codec) -> bool {
+        match self {
+            Container::Mkv => true,
+            Container::Webm => matches!(
+                codec,
+                Codec::Av1 | Codec::Opus | Codec::Vorbis | Codec::Vp8 | Codec::Vp9 | Codec::WebVtt
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum SplitMode {
+    /// Split the output file into parts of a given size, such as 2000M.
+    #[serde(rename = "size")]
+    Size(String),
+    /// Split the output file into parts of a given duration, such as 00:20:00.
+    #[serde(rename = "duration")]
+    Duration(String),
+}
+
+impl SplitMode {
+    /// Get the value of the split argument to be passed to MkvMerge.
+    pub fn as_mkvmerge_argument(&self) -> String {
+        match self {
+            SplitMode::Size(size) => format!("size:{size}"),
+            SplitMode::Duration(duration) => format!("duration:{duration}"),
+        }
+    }
+
+    /// Check whether the split size or duration is in a format accepted by MkvMerge.
+    pub fn is_valid(&self) -> bool {
+        let (value, pattern) = match self {
+            SplitMode::Size(size) => (size, r"^\d+[KkMmGg]?$"),
+            SplitMode::Duration(duration) => (
+                duration,
+                r"^(\d+:)?\d{1,2}:\d{2}(\.\d{1,9})?$|^\d+(\.\d{1,9})?s$",
+            ),
+        };
+
+        Regex::new(pattern).unwrap().is_match(value)
+    }
 }
 
-#[derive(Default, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub enum DeletionOptions {
     /// Delete the file using the default method.
     Delete,
@@ -91,31 +186,102 @@ pub enum DeletionOptions {
     None,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct MiscParams {
+    /// Should the command line parameters passed to external tools be logged?
+    pub debug_commands: Option<bool>,
+    /// Should the JSON MediaInfo output be exported to a file?
+    pub export_mediainfo_json: Option<bool>,
     /// The method to be used when removing the original files, if specified.
     pub remove_original_file: Option<DeletionOptions>,
     /// The method to be used when removing the temporary files, if specified.
     pub remove_temp_files: Option<DeletionOptions>,
     // Should the title of the media file be set?
     pub set_file_title: Option<bool>,
+    /// The template used to build the title embedded within the media file, when it is being set.
+    /// The %n% tag will be replaced with the title from the output names file, and the %index% tag with the
+    /// padded index, if any. If unspecified, the title from the output names file will be used as-is.
+    pub file_title_template: Option<String>,
+    /// Any substitutions to be applied to the title of the media file, based on the tracks that were kept.
+    /// These are applied after the tracks have been filtered and converted, and do not affect the output file name.
+    pub metadata_substitutions: Option<Vec<MetadataSubstitution>>,
+    /// Should any existing title of the media file be removed? This only applies if the title is not being set.
+    pub clear_file_title: Option<bool>,
+    /// Should any existing titles of the tracks be removed?
+    pub clear_track_titles: Option<bool>,
+    /// Should the first kept audio and subtitle tracks automatically be set as the default tracks?
+    /// Any default flag explicitly specified within the track parameters will take precedence.
+    pub auto_default_tracks: Option<bool>,
     /// Should the computer be shutdown after the processing is complete?
     pub shutdown_upon_completion: Option<bool>,
+    /// The container format of the output files. If unspecified, MKV files will be created.
+    pub output_container: Option<Container>,
+    /// Should the output file be split into several parts, and if so how?
+    pub split: Option<SplitMode>,
+    /// Should the track statistics tags (such as BPS and DURATION) be omitted by MkvMerge?
+    pub disable_track_statistics_tags: Option<bool>,
+    /// Should the output files be byte-identical across runs with the same input files and options?
+    /// This omits the muxing date and derives the segment UIDs from the name of the input file.
+    pub reproducible: Option<bool>,
+    /// Should the tracks be written in the same order as they appeared within the original file?
+    /// If unspecified, the tracks will be written in the order in which they were kept.
+    pub preserve_track_order: Option<bool>,
+    /// Any extra arguments to be passed to MkvMerge, after the global options and before the input files.
+    /// These are passed verbatim and are not sanitized or validated in any way.
+    pub extra_mkvmerge_args: Option<Vec<String>>,
     /// The path to the tags file.
     /// If the path is not specified, or is invalid, then no tags will be added.
     pub tags_path: Option<String>,
+    /// Should a tags file containing the title of the media file be generated?
+    /// This only applies if no tags path is specified.
+    pub generate_tags: Option<bool>,
+    /// The path to which a JSON report of the batch should be written.
+    /// If the path is not specified then no report will be written.
+    pub report_path: Option<String>,
     /// The paths to any processes that should be run before or after this processing step.
     pub run: Option<Vec<ProcessRun>>,
+    /// The number of times a failed MkvToolNix or FFMPEG process should be retried.
+    /// If unspecified, failed processes will not be retried.
+    pub retry_count: Option<u8>,
+    /// The number of seconds to wait between retries.
+    pub retry_delay_secs: Option<u64>,
+    /// Should the processing continue if a run command fails?
+    /// If unspecified, the processing of the media file will be aborted.
+    pub continue_on_run_error: Option<bool>,
     /// Should certain types of media file be automatically muxed to MKV files before processing?
     pub pre_mux_media_files: Option<bool>,
+    /// The list of extensions of the media files that should be muxed to MKV files before processing.
+    /// If unspecified, only mp4 files will be muxed.
+    pub pre_mux_extensions: Option<Vec<String>>,
+    /// Should the commands be logged rather than executed? This is set via the command line.
+    #[serde(skip)]
+    pub dry_run: bool,
+    /// Should the profile be validated without processing any files? This is set via the command line.
+    #[serde(skip)]
+    pub validate_only: bool,
+    /// Should the remaining media files be processed if a media file could not be processed?
+    /// If unspecified, the failure will be recorded and the processing will continue.
+    /// Set this to `false` to stop processing after the first failure.
+    pub continue_on_error: Option<bool>,
+    /// The maximum number of media files that may be processed concurrently.
+    /// If unspecified, the media files will be processed sequentially.
+    pub max_parallel_files: Option<usize>,
+    /// Should any temporary directories left behind by a previous run be removed before processing begins?
+    /// This should not be used if several instances of the program share the same temporary directory.
+    pub clean_temp_on_start: Option<bool>,
+    /// Should any track that has the same type, language, codec and channel count as a previously kept track be skipped?
+    pub dedupe_tracks: Option<bool>,
 }
 
 pub trait PredicateFilterMatch<T> {
     fn is_match(&self, needle: T) -> bool;
 }
 
-#[derive(Clone, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
 pub enum ProcessRun {
+    // A command to be run prior to extracting any tracks from the input file.
+    #[serde(rename = "pre_extract")]
+    PreExtract(Vec<String>),
     // A command to be run prior to converting any tracks using FFMPEG.
     #[serde(rename = "pre_convert")]
     PreConvert(Vec<String>),
@@ -130,9 +296,81 @@ pub enum ProcessRun {
     PostMux(Vec<String>),
 }
 
-#[derive(Deserialize)]
+impl ProcessRun {
+    /// Get the arguments of the command. The first argument is always the path to the command.
+    pub fn args(&self) -> &[String] {
+        match self {
+            ProcessRun::PreExtract(args)
+            | ProcessRun::PreConvert(args)
+            | ProcessRun::PreMux(args)
+            | ProcessRun::PostConvert(args)
+            | ProcessRun::PostMux(args) => args,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TrackChannelPredicate {
+    /// The exact number of channels that a track must have.
+    pub equals: Option<u32>,
+    /// The maximum number of channels that a track may have.
+    pub max: Option<u32>,
+    /// The minimum number of channels that a track must have.
+    pub min: Option<u32>,
+}
+
+impl TrackChannelPredicate {
+    pub fn is_empty(&self) -> bool {
+        self.equals.is_none() && self.max.is_none() && self.min.is_none()
+    }
+}
+
+impl PredicateFilterMatch<u32> for TrackChannelPredicate {
+    /// Checks to see if a given track channel count is a match against the specified filters.
+    ///
+    /// # Returns
+    ///
+    /// True if track channel count was a match for the filters, false otherwise.
+    fn is_match(&self, needle: u32) -> bool {
+        self.equals.is_none_or(|c| needle == c)
+            && self.max.is_none_or(|c| needle <= c)
+            && self.min.is_none_or(|c| needle >= c)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TrackCodecPredicate {
+    /// A list of the codecs that have been specified in the filters.
+    pub ids: Vec<Codec>,
+    /// Should tracks with the specified codecs be excluded, rather than included?
+    #[serde(default)]
+    pub exclude: bool,
+}
+
+impl TrackCodecPredicate {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+impl PredicateFilterMatch<&Codec> for TrackCodecPredicate {
+    /// Checks to see if a given track codec is a match against the specified filters.
+    ///
+    /// # Returns
+    ///
+    /// True if track codec was a match for the filters, false otherwise.
+    fn is_match(&self, needle: &Codec) -> bool {
+        self.is_empty() || self.ids.contains(needle) != self.exclude
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct TrackIndexPredicate {
+    /// A list of the track indices that have been specified in the filters.
     ids: Vec<usize>,
+    /// Should tracks with the specified indices be excluded, rather than included?
+    #[serde(default)]
+    exclude: bool,
 }
 
 impl TrackIndexPredicate {
@@ -148,11 +386,48 @@ impl PredicateFilterMatch<usize> for TrackIndexPredicate {
     ///
     /// True if track language ID was a match for the filters, false otherwise.
     fn is_match(&self, needle: usize) -> bool {
-        self.is_empty() || self.ids.contains(&needle)
+        self.is_empty() || self.ids.contains(&needle) != self.exclude
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+pub struct TrackResolutionPredicate {
+    /// The maximum height of the track, in pixels.
+    pub max_height: Option<u32>,
+    /// The maximum width of the track, in pixels.
+    pub max_width: Option<u32>,
+    /// The minimum height of the track, in pixels.
+    pub min_height: Option<u32>,
+    /// The minimum width of the track, in pixels.
+    pub min_width: Option<u32>,
+}
+
+impl TrackResolutionPredicate {
+    pub fn is_empty(&self) -> bool {
+        self.max_height.is_none()
+            && self.max_width.is_none()
+            && self.min_height.is_none()
+            && self.min_width.is_none()
+    }
+}
+
+impl PredicateFilterMatch<(u32, u32)> for TrackResolutionPredicate {
+    /// Checks to see if a given track resolution (width, height) is a match against the specified filters.
+    ///
+    /// # Returns
+    ///
+    /// True if track resolution was a match for the filters, false otherwise.
+    fn is_match(&self, needle: (u32, u32)) -> bool {
+        let (width, height) = needle;
+
+        self.max_height.is_none_or(|h| height <= h)
+            && self.max_width.is_none_or(|w| width <= w)
+            && self.min_height.is_none_or(|h| height >= h)
+            && self.min_width.is_none_or(|w| width >= w)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct TrackTitlePredicate {
     /// The predicate filter type.
     #[serde(default = "TrackTitlePredicateCondition::default")]
@@ -264,7 +539,7 @@ impl PredicateFilterMatch<&str> for TrackTitlePredicate {
     }
 }
 
-#[derive(Default, Deserialize, Eq, PartialEq)]
+#[derive(Default, Deserialize, Serialize, Eq, PartialEq)]
 pub enum TrackTitlePredicateCondition {
     /// If all of the filters are a match, then the title will be considered as matching.
     #[default]
@@ -275,7 +550,7 @@ pub enum TrackTitlePredicateCondition {
     Or,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum TrackTitlePredicateType {
     /// A fuzzy match - a match will be counted if the title contains the string.
     Contains(String),
@@ -285,10 +560,13 @@ pub enum TrackTitlePredicateType {
     Regex(String),
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct TrackLanguagePredicate {
     /// A list of language ID codes that have been specified in the filters.
     pub ids: Vec<String>,
+    /// Should tracks with the specified languages be excluded, rather than included?
+    #[serde(default)]
+    pub exclude: bool,
 }
 
 impl TrackLanguagePredicate {
@@ -304,18 +582,33 @@ impl PredicateFilterMatch<&str> for TrackLanguagePredicate {
     ///
     /// True if track language ID was a match for the filters, false otherwise.
     fn is_match(&self, needle: &str) -> bool {
-        self.is_empty() || self.ids.contains(&needle.to_string())
+        self.is_empty() || self.ids.contains(&needle.to_string()) != self.exclude
     }
 }
 
-#[derive(Default, Deserialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub enum TrackPredicate {
+    /// A match will be counted if all of the inner predicates are a match.
+    #[serde(rename = "all")]
+    All(Vec<TrackPredicate>),
+    /// A match will be counted if any of the inner predicates are a match.
+    #[serde(rename = "any")]
+    Any(Vec<TrackPredicate>),
+    /// Filter by track channel count.
+    #[serde(rename = "channels")]
+    Channels(TrackChannelPredicate),
+    /// Filter by track codec.
+    #[serde(rename = "codec")]
+    Codec(TrackCodecPredicate),
     /// Filter by track indices.
     #[serde(rename = "index")]
     Index(TrackIndexPredicate),
     /// Filter by track language code.
     #[serde(rename = "language")]
     Language(TrackLanguagePredicate),
+    /// Filter by track resolution.
+    #[serde(rename = "resolution")]
+    Resolution(TrackResolutionPredicate),
     /// Filter by track title.
     #[serde(rename = "title")]
     Title(TrackTitlePredicate),
@@ -331,15 +624,135 @@ impl TrackPredicate {
     ///
     /// True if the regular expression were valid, false otherwise.
     pub fn initialize_regex(&mut self) -> bool {
-        if let TrackPredicate::Title(tft) = self {
-            tft.initialize_regex()
-        } else {
-            true
+        match self {
+            TrackPredicate::All(ps) | TrackPredicate::Any(ps) => {
+                ps.iter_mut().all(|p| p.initialize_regex())
+            }
+            TrackPredicate::Title(tft) => tft.initialize_regex(),
+            _ => true,
+        }
+    }
+
+    /// Check whether a given track is a match for the predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the track within the media file.
+    /// * `track` - The track to be checked.
+    ///
+    /// # Returns
+    ///
+    /// True if the track was a match for the predicate, false otherwise.
+    pub fn is_track_match(&self, index: usize, track: &MediaFileTrack) -> bool {
+        match self {
+            TrackPredicate::All(ps) => ps.iter().all(|p| p.is_track_match(index, track)),
+            TrackPredicate::Any(ps) => ps.iter().any(|p| p.is_track_match(index, track)),
+            TrackPredicate::Channels(c) => c.is_match(track.channels),
+            TrackPredicate::Codec(c) => c.is_match(&track.codec),
+            TrackPredicate::Index(i) => i.is_match(index - 1),
+            TrackPredicate::Language(l) => l.is_match(&track.language),
+            TrackPredicate::Resolution(r) => r.is_match((track.width, track.height)),
+            TrackPredicate::Title(t) => t.is_match(&track.title),
+            TrackPredicate::None => true,
+        }
+    }
+
+    /// Check whether the predicate, and any nested predicates, are valid.
+    ///
+    /// # Returns
+    ///
+    /// True if the predicate is valid, false otherwise.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            TrackPredicate::All(ps) | TrackPredicate::Any(ps) => {
+                !ps.is_empty() && ps.iter().all(|p| p.is_valid())
+            }
+            TrackPredicate::Channels(c) => !c.is_empty(),
+            TrackPredicate::Codec(c) => !c.is_empty(),
+            TrackPredicate::Index(i) => !i.is_empty(),
+            TrackPredicate::Language(l) => !l.is_empty(),
+            TrackPredicate::Resolution(r) => !r.is_empty(),
+            TrackPredicate::Title(_) => true,
+            TrackPredicate::None => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(try_from = "TrackRetentionValue", into = "TrackRetentionValue")]
+pub enum TrackRetention {
+    /// Every matching track will be retained.
+    #[default]
+    All,
+    /// A specific number of matching tracks will be retained.
+    Count(usize),
+    /// No tracks will be retained.
+    None,
+}
+
+/// The raw profile value of a [`TrackRetention`], which may be either a count or a keyword.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum TrackRetentionValue {
+    Count(usize),
+    Keyword(String),
+}
+
+impl From<TrackRetention> for TrackRetentionValue {
+    fn from(value: TrackRetention) -> Self {
+        match value {
+            TrackRetention::All => TrackRetentionValue::Keyword("all".to_string()),
+            TrackRetention::Count(count) => TrackRetentionValue::Count(count),
+            TrackRetention::None => TrackRetentionValue::Keyword("none".to_string()),
+        }
+    }
+}
+
+impl TryFrom<TrackRetentionValue> for TrackRetention {
+    type Error = String;
+
+    fn try_from(value: TrackRetentionValue) -> Result<Self, Self::Error> {
+        match value {
+            TrackRetentionValue::Count(count) => Ok(TrackRetention::Count(count)),
+            TrackRetentionValue::Keyword(keyword) => match keyword.to_lowercase().as_str() {
+                "all" => Ok(TrackRetention::All),
+                "none" => Ok(TrackRetention::None),
+                _ => Err(format!(
+                    "invalid track retention '{keyword}', expected a number, \"all\" or \"none\""
+                )),
+            },
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub enum RetainMode {
+    /// Exactly the target number of tracks must be retained.
+    #[default]
+    Exact,
+    /// No more than the target number of tracks may be retained.
+    AtMost,
+    /// At least the target number of tracks must be retained. Any further matching tracks will also be retained.
+    AtLeast,
+}
+
+impl RetainMode {
+    /// Check whether a number of retained tracks is acceptable for a given target.
+    ///
+    /// # Arguments
+    ///
+    /// * `retained` - The number of retained tracks.
+    /// * `target` - The target number of tracks.
+    pub fn is_satisfied(&self, retained: usize, target: usize) -> bool {
+        match self {
+            RetainMode::Exact => retained == target,
+            RetainMode::AtMost => retained <= target,
+            RetainMode::AtLeast => retained >= target,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct UnifiedAudioParams {
     /// The type of filter that should be applied to this track.
     #[serde(default = "TrackPredicate::default")]
@@ -349,11 +762,18 @@ pub struct UnifiedAudioParams {
     /// If the language is undefined, what should the language be
     /// assumed as being?
     pub default_language: Option<String>,
-    /// The number of tracks of this type to retain, in total.
-    pub total_to_retain: Option<usize>,
+    /// The number of tracks of this type to retain, in total. This may be a number, "all" or "none".
+    /// If unspecified, all matching tracks will be retained.
+    #[serde(default)]
+    pub total_to_retain: TrackRetention,
+    /// How the total number of tracks to retain should be interpreted. If unspecified, exactly that number must be retained.
+    pub retain_mode: Option<RetainMode>,
+    /// The language ID of the track that should be flagged as the default track of this type.
+    /// The first retained track with a matching language will be flagged as the default.
+    pub default_for_language: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct UnifiedSubtitleParams {
     /// The type of filter that should be applied to this track.
     #[serde(default = "TrackPredicate::default")]
@@ -363,18 +783,115 @@ pub struct UnifiedSubtitleParams {
     /// If the language is undefined, what should the language be
     /// assumed as being?
     pub default_language: Option<String>,
-    /// The number of tracks of this type to retain, in total.
-    pub total_to_retain: Option<usize>,
+    /// The number of tracks of this type to retain, in total. This may be a number, "all" or "none".
+    /// If unspecified, all matching tracks will be retained.
+    #[serde(default)]
+    pub total_to_retain: TrackRetention,
+    /// How the total number of tracks to retain should be interpreted. If unspecified, exactly that number must be retained.
+    pub retain_mode: Option<RetainMode>,
+    /// The language ID of the track that should be flagged as the default track of this type.
+    /// The first retained track with a matching language will be flagged as the default.
+    pub default_for_language: Option<String>,
+    /// The parameters used to automatically flag subtitle tracks as forced, if any.
+    pub forced_detection: Option<ForcedSubtitleParams>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ForcedSubtitleParams {
+    /// Any subtitle track with a title containing one of these strings will be flagged as forced.
+    /// The comparison is not case-sensitive.
+    pub title_patterns: Option<Vec<String>>,
+    /// Any subtitle track smaller than this fraction of the size of the largest subtitle track
+    /// with the same language will be flagged as forced.
+    pub max_size_ratio: Option<f64>,
+}
+
+impl ForcedSubtitleParams {
+    /// Check whether a track title matches any of the forced subtitle title patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The title of the track.
+    pub fn is_title_match(&self, title: &str) -> bool {
+        let title = title.to_lowercase();
+        self.title_patterns
+            .iter()
+            .flatten()
+            .any(|p| title.contains(&p.to_lowercase()))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MetadataSubstitution {
+    /// The width that a kept video track must have for the substitution to apply.
+    pub video_width: Option<u32>,
+    /// The height that a kept video track must have for the substitution to apply.
+    pub video_height: Option<u32>,
+    /// The codec that a kept audio track must have for the substitution to apply.
+    pub audio_codec: Option<Codec>,
+    /// The number of channels that a kept audio track must have for the substitution to apply.
+    pub audio_channels: Option<u32>,
+    /// The template used to build the new title. The %n% tag will be replaced with the current title.
+    /// The %width%, %height%, %audio_codec% and %channels% tags will be replaced with the properties
+    /// of the first matching video and audio tracks.
+    pub template: String,
 }
 
-#[derive(Deserialize)]
+impl MetadataSubstitution {
+    /// Find the first track of a given type that meets the conditions of the substitution.
+    ///
+    /// # Arguments
+    ///
+    /// * `tracks` - The tracks that were kept.
+    /// * `track_type` - The [`TrackType`] of the track.
+    ///
+    /// # Returns
+    ///
+    /// An option containing the matching track, if one was found.
+    pub fn find_track<'a>(
+        &self,
+        tracks: &'a [MediaFileTrack],
+        track_type: TrackType,
+    ) -> Option<&'a MediaFileTrack> {
+        tracks
+            .iter()
+            .filter(|t| t.track_type == track_type)
+            .find(|t| match track_type {
+                TrackType::Video => {
+                    self.video_width.is_none_or(|w| t.width == w)
+                        && self.video_height.is_none_or(|h| t.height == h)
+                }
+                TrackType::Audio => {
+                    self.audio_codec.as_ref().is_none_or(|c| t.codec == *c)
+                        && self.audio_channels.is_none_or(|c| t.channels == c)
+                }
+                _ => true,
+            })
+    }
+
+    /// Check whether the kept tracks meet the conditions of the substitution.
+    ///
+    /// # Arguments
+    ///
+    /// * `tracks` - The tracks that were kept.
+    pub fn is_match(&self, tracks: &[MediaFileTrack]) -> bool {
+        let video_matched = (self.video_width.is_none() && self.video_height.is_none())
+            || self.find_track(tracks, TrackType::Video).is_some();
+        let audio_matched = (self.audio_codec.is_none() && self.audio_channels.is_none())
+            || self.find_track(tracks, TrackType::Audio).is_some();
+
+        video_matched && audio_matched
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct UnifiedOtherTrackParams {
     /// Should "other" be imported from the original file?
     /// These are tracks that are not of type: audio, subtitle or video.
     pub import_from_original: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct UnifiedVideoParams {
     /// The type of filter that should be applied to this track.
     #[serde(default = "TrackPredicate::default")]
@@ -384,8 +901,15 @@ pub struct UnifiedVideoParams {
     /// If the language is undefined, what should the language be
     /// assumed as being?
     pub default_language: Option<String>,
-    /// The number of tracks of this type to retain, in total.
-    pub total_to_retain: Option<usize>,
+    /// The number of tracks of this type to retain, in total. This may be a number, "all" or "none".
+    /// If unspecified, all matching tracks will be retained.
+    #[serde(default)]
+    pub total_to_retain: TrackRetention,
+    /// How the total number of tracks to retain should be interpreted. If unspecified, exactly that number must be retained.
+    pub retain_mode: Option<RetainMode>,
+    /// The language ID of the track that should be flagged as the default track of this type.
+    /// The first retained track with a matching language will be flagged as the default.
+    pub default_for_language: Option<String>,
 }
 
 fn array_to_lowercase_string_vec<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
@@ -400,3 +924,91 @@ where
 
     Ok(Some(vec))
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Build a minimal set of [`UnifiedParams`], with the given JSON objects merged into the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - A JSON object containing the sections to be replaced, such as `misc`.
+    pub fn params_from_json(overrides: serde_json::Value) -> UnifiedParams {
+        let mut json = serde_json::json!({
+            "audio_tracks": {},
+            "subtitle_tracks": {},
+            "video_tracks": {},
+            "other_tracks": { "import_from_original": false },
+            "attachments": {
+                "import_from_original": false,
+                "import_original_extensions": [],
+                "import_folder_extensions": []
+            },
+            "chapters": { "import_from_original": false, "create_if_not_present": false },
+            "misc": {}
+        });
+
+        for (key, value) in overrides.as_object().unwrap() {
+            json[key] = value.clone();
+        }
+
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn language_predicate_includes_listed_languages() {
+        let predicate: TrackLanguagePredicate =
+            serde_json::from_value(serde_json::json!({ "ids": ["eng", "jpn"] })).unwrap();
+
+        assert!(predicate.is_match("eng"));
+        assert!(predicate.is_match("jpn"));
+        assert!(!predicate.is_match("fre"));
+    }
+
+    #[test]
+    fn language_predicate_excludes_listed_languages() {
+        let predicate: TrackLanguagePredicate =
+            serde_json::from_value(serde_json::json!({ "ids": ["eng", "jpn"], "exclude": true }))
+                .unwrap();
+
+        assert!(!predicate.is_match("eng"));
+        assert!(!predicate.is_match("jpn"));
+        assert!(predicate.is_match("fre"));
+    }
+
+    #[test]
+    fn index_predicate_includes_listed_indices() {
+        let predicate: TrackIndexPredicate =
+            serde_json::from_value(serde_json::json!({ "ids": [0, 2], "exclude": false })).unwrap();
+
+        assert!(predicate.is_match(0));
+        assert!(predicate.is_match(2));
+        assert!(!predicate.is_match(1));
+    }
+
+    #[test]
+    fn index_predicate_excludes_listed_indices() {
+        let predicate: TrackIndexPredicate =
+            serde_json::from_value(serde_json::json!({ "ids": [0, 2], "exclude": true })).unwrap();
+
+        assert!(!predicate.is_match(0));
+        assert!(!predicate.is_match(2));
+        assert!(predicate.is_match(1));
+    }
+
+    #[test]
+    fn empty_predicates_match_everything_regardless_of_exclude() {
+        for exclude in [false, true] {
+            let language: TrackLanguagePredicate =
+                serde_json::from_value(serde_json::json!({ "ids": [], "exclude": exclude }))
+                    .unwrap();
+            let index: TrackIndexPredicate =
+                serde_json::from_value(serde_json::json!({ "ids": [], "exclude": exclude }))
+                    .unwrap();
+
+            assert!(language.is_match("eng"));
+            assert!(index.is_match(0));
+        }
+    }
+}
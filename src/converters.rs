@@ -1,11 +1,11 @@
 use crate::{
     conversion_params::{
         audio::AudioConvertParams, params_trait::ConversionParams, subtitle::SubtitleConvertParams,
-        video::VideoConvertParams,
+        unified::MiscParams, video::VideoConvertParams,
     },
     logger,
     media_file::MediaFileTrack,
-    paths,
+    paths, utils,
 };
 
 use std::process::Command;
@@ -20,15 +20,17 @@ const FAIL_ERROR_CODE: i32 = 1;
 /// * `file_in` - The path to the input file.
 /// * `file_out` - The path to the output file.
 /// * `params` - The parameters to be used for encoding the output file.
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
 pub fn convert_audio_file(
     track: &MediaFileTrack,
     file_in: &str,
     file_out: &str,
     params: &AudioConvertParams,
+    misc: &MiscParams,
 ) -> bool {
     if let Some(args) = params.as_ffmpeg_argument_list(track, file_in, file_out) {
         // Run FFMPEG with the specified parameters.
-        run_ffmpeg(&args) == 0
+        run_ffmpeg(&args, misc) == 0
     } else {
         false
     }
@@ -67,20 +69,35 @@ pub fn convert_video_file(file_in: &str, file_out: &str, params: &VideoConvertPa
 /// # Arguments
 ///
 /// * `args` - A list of the command-line arguments to be passed to FFMPEG.
-fn run_ffmpeg(args: &[String]) -> i32 {
-    let output = Command::new(&paths::PATHS.ffmpeg).args(args).output();
-    let result = match &output {
-        Ok(o) => o.status.code().unwrap_or(FAIL_ERROR_CODE),
-        Err(_) => FAIL_ERROR_CODE,
-    };
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
+fn run_ffmpeg(args: &[String], misc: &MiscParams) -> i32 {
+    if misc.dry_run {
+        logger::log(
+            format!("[DRY RUN] \"{}\" {}", paths::PATHS.ffmpeg, args.join(" ")),
+            true,
+        );
+        return 0;
+    }
+
+    let (result, stderr) = utils::run_with_retries(
+        "FFMPEG",
+        misc,
+        |code| code != 0,
+        || match Command::new(&paths::PATHS.ffmpeg).args(args).output() {
+            Ok(o) => (
+                o.status.code().unwrap_or(FAIL_ERROR_CODE),
+                String::from_utf8_lossy(&o.stderr).to_string(),
+            ),
+            Err(e) => (FAIL_ERROR_CODE, format!("{e:?}")),
+        },
+    );
 
     if result == FAIL_ERROR_CODE {
         logger::log(
             "FFMPEG was not successfully executed and yielded the following output:",
             false,
         );
-        let out = output.unwrap();
-        logger::log_output_lines(&String::from_utf8_lossy(&out.stderr), false);
+        logger::log_output_lines(&stderr, false);
     }
 
     result
@@ -92,7 +109,8 @@ fn run_ffmpeg(args: &[String]) -> i32 {
 ///
 /// * `file_in` - The path to the input file.
 /// * `file_out` - The path to the output file.
-pub fn remux_media_file(file_in: &str, file_out: &str) -> bool {
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
+pub fn remux_media_file(file_in: &str, file_out: &str, misc: &MiscParams) -> bool {
     let args = [
         "-i".to_string(),
         file_in.to_string(),
@@ -101,5 +119,5 @@ pub fn remux_media_file(file_in: &str, file_out: &str) -> bool {
         file_out.to_string(),
     ];
 
-    run_ffmpeg(&args) == 0
+    run_ffmpeg(&args, misc) == 0
 }
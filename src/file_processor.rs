@@ -2,32 +2,55 @@ use crate::{
     conversion_params::unified::{DeletionOptions, UnifiedParams},
     converters,
     input_profile::InputProfile,
-    logger,
+    logger::{self, Level},
     media_file::MediaFile,
+    paths,
+    progress::Progress,
+    report::{self, FileReport},
     utils,
 };
 
+use hashbrown::{HashMap, HashSet};
 use lexical_sort::{natural_cmp, StringSort};
-use serde_derive::Deserialize;
+use rayon::{prelude::*, ThreadPoolBuilder};
+use serde_derive::{Deserialize, Serialize};
 use std::{
-    fs::{self, DirEntry, File},
-    io::{BufRead, BufReader, Error},
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
     time::Instant,
 };
+use unicode_normalization::UnicodeNormalization;
+use walkdir::{DirEntry, WalkDir};
 
-/// A list of valid extension that can be automatically processed.
+/// A list of valid extension that can be automatically processed, unless otherwise specified by the profile.
 const VALID_EXTENSIONS: [&str; 1] = ["mkv"];
 /// A list of valid extensions that can be automatically converted into MKV files in pre-processing.
 const VALID_REMUX_EXTENSIONS: [&str; 1] = ["mp4"];
 /// The file list early stop clause.
 const STOP_CLAUSE: &str = "###STOP###";
+/// The default separator between the index and the name of an output file.
+const DEFAULT_INDEX_SEPARATOR: &str = " – ";
 
-#[derive(Clone, Copy, Deserialize)]
-pub enum PadType {
-    One,
-    Ten,
-    Hundred,
-    Thousand,
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub enum CollisionMode {
+    /// Abort the processing if two or more output files would share the same path.
+    Fail,
+    /// Append a numeric suffix to any output file that would share the path of a previous file.
+    Suffix,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+pub enum SortMode {
+    /// Sort the input files naturally, so that any numbers are compared by their value.
+    #[default]
+    Natural,
+    /// Sort the input files by strictly comparing the characters of their paths.
+    Lexical,
+    /// Sort the input files by the numbers within their file names only.
+    /// Any files with identical numbers will be sorted lexically.
+    Numeric,
 }
 
 pub struct FileProcessor {
@@ -35,6 +58,7 @@ pub struct FileProcessor {
     pub input_paths: Vec<String>,
     pub output_paths: Vec<String>,
     pub titles: Vec<String>,
+    pub indices: Vec<Option<String>>,
 }
 
 impl FileProcessor {
@@ -46,6 +70,7 @@ impl FileProcessor {
             input_paths: vec![],
             output_paths: vec![],
             titles: vec![],
+            indices: vec![],
         };
 
         // If one or more required paths were invalid then we can't continue.
@@ -59,12 +84,22 @@ impl FileProcessor {
             return None;
         }
 
+        // Ensure that no output file will overwrite another.
+        if !s.resolve_output_collisions(profile) {
+            return None;
+        }
+
         // Build the list of input file paths.
         s.build_input_list(profile);
         if s.input_paths.is_empty() {
             return None;
         }
 
+        // Pair the input files with the output names by their names, if required.
+        if profile.match_by_name.unwrap_or(false) && !s.pair_inputs_by_name() {
+            return None;
+        }
+
         // We must now check that the number of files in the input
         // directory is equal to the number of entries from the output file list.
         if s.input_paths.len() != s.output_paths.len() {
@@ -93,16 +128,22 @@ impl FileProcessor {
     ///
     /// * `profile` - The [`InputProfile`] specified when running the program.
     fn build_input_list(&mut self, profile: &InputProfile) {
-        let read = fs::read_dir(&profile.input_dir);
-        assert!(
-            read.is_ok(),
-            "Failed to read input files directory: {read:?}"
-        );
+        // If a pattern was specified, it will be used in place of the directory scan.
+        let paths = match &profile.input_pattern {
+            Some(pattern) => FileProcessor::glob_input_dir(profile, pattern),
+            None => FileProcessor::walk_input_dir(profile),
+        };
+
+        // The extensions specified by the profile take precedence over the defaults.
+        let extensions: Vec<String> = match &profile.input_extensions {
+            Some(exts) if !exts.is_empty() => exts.iter().map(|e| e.to_lowercase()).collect(),
+            _ => VALID_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+        };
 
         // Add all of the matching files into the file list.
-        for path in read
-            .unwrap()
-            .filter_map(|p| FileProcessor::filter_by_file_extension(p, &VALID_EXTENSIONS))
+        for path in paths
+            .iter()
+            .filter_map(|p| FileProcessor::filter_by_file_extension(p, &extensions))
         {
             self.input_paths.push(path);
         }
@@ -116,8 +157,16 @@ impl FileProcessor {
             return;
         }
 
-        // Sort the input file paths using a natural sorting algorithm.
-        self.input_paths.string_sort_unstable(natural_cmp);
+        // Sort the input file paths using the sorting mode specified by the profile.
+        match profile.sort_mode.unwrap_or_default() {
+            SortMode::Natural => self.input_paths.string_sort_unstable(natural_cmp),
+            SortMode::Lexical => self.input_paths.sort_unstable(),
+            SortMode::Numeric => self.input_paths.sort_unstable_by(|a, b| {
+                FileProcessor::file_name_numbers(a)
+                    .cmp(&FileProcessor::file_name_numbers(b))
+                    .then_with(|| a.cmp(b))
+            }),
+        }
 
         logger::log(
             format!(
@@ -134,11 +183,158 @@ impl FileProcessor {
 
         // If the stop clause has been specified then we need to truncate
         // the input file list to be the same length as the output file list.
-        if self.has_stop_clause {
+        // This isn't needed when pairing by name, as any unmatched files will be skipped.
+        if self.has_stop_clause && !profile.match_by_name.unwrap_or(false) {
             self.input_paths.truncate(self.output_paths.len());
         }
     }
 
+    /// Pair the input files with the output names by their normalized names, rather than by their positions.
+    /// The input file list will be reordered to match the output list, and any entries without a match
+    /// will be reported and removed.
+    ///
+    /// # Returns
+    ///
+    /// True if the files could be unambiguously paired, false otherwise.
+    fn pair_inputs_by_name(&mut self) -> bool {
+        // Group the input files by their normalized file stems.
+        let mut inputs: HashMap<String, Vec<String>> = HashMap::new();
+        for path in &self.input_paths {
+            let stem = Path::new(path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            inputs
+                .entry(FileProcessor::normalize_name(&stem))
+                .or_default()
+                .push(path.clone());
+        }
+
+        // Several input files with the same name can't be told apart.
+        let mut ambiguous: Vec<&String> = inputs
+            .values()
+            .filter(|paths| paths.len() > 1)
+            .flatten()
+            .collect();
+        if !ambiguous.is_empty() {
+            ambiguous.sort_unstable();
+            logger::log(
+                "The following input files can't be paired by name, as their names are not unique:",
+                true,
+            );
+            for path in ambiguous {
+                logger::log(format!("\t{path}"), true);
+            }
+            return false;
+        }
+
+        let mut input_paths = Vec::with_capacity(self.output_paths.len());
+        let mut output_paths = Vec::with_capacity(self.output_paths.len());
+        let mut titles = Vec::with_capacity(self.output_paths.len());
+        let mut indices = Vec::with_capacity(self.output_paths.len());
+        let mut unmatched_outputs = Vec::new();
+
+        for (i, title) in self.titles.iter().enumerate() {
+            match inputs.remove(&FileProcessor::normalize_name(title)) {
+                Some(mut paths) => {
+                    input_paths.push(paths.remove(0));
+                    output_paths.push(self.output_paths[i].clone());
+                    titles.push(title.clone());
+                    indices.push(self.indices[i].clone());
+                }
+                None => unmatched_outputs.push(title),
+            }
+        }
+
+        if !unmatched_outputs.is_empty() {
+            logger::log(
+                "The following output names have no matching input file and will be skipped:",
+                true,
+            );
+            for title in unmatched_outputs {
+                logger::log(format!("\t{title}"), true);
+            }
+        }
+
+        let mut unmatched_inputs: Vec<String> = inputs.into_values().flatten().collect();
+        if !unmatched_inputs.is_empty() {
+            unmatched_inputs.sort_unstable();
+            logger::log(
+                "The following input files have no matching output name and will be skipped:",
+                true,
+            );
+            for path in unmatched_inputs {
+                logger::log(format!("\t{path}"), true);
+            }
+        }
+
+        if input_paths.is_empty() {
+            logger::log(
+                "None of the input files could be paired with an output name.",
+                true,
+            );
+            return false;
+        }
+
+        logger::log(
+            format!(
+                "{} input file{} paired with an output name.",
+                input_paths.len(),
+                if input_paths.len() != 1 {
+                    "s were"
+                } else {
+                    " was"
+                }
+            ),
+            false,
+        );
+
+        self.input_paths = input_paths;
+        self.output_paths = output_paths;
+        self.titles = titles;
+        self.indices = indices;
+
+        true
+    }
+
+    /// Normalize a name so that it can be compared with other names, ignoring any differences
+    /// in case, accents, punctuation and whitespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to be normalized.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the normalized name.
+    fn normalize_name(name: &str) -> String {
+        name.nfkd()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    /// Get the numbers contained within the name of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing the value of each sequence of digits in the file name, in order.
+    fn file_name_numbers(path: &str) -> Vec<u64> {
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        name.split(|c: char| !c.is_ascii_digit())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().unwrap_or(u64::MAX))
+            .collect()
+    }
+
     /// Build the output file list from the parameter specified by the [`InputProfile`].
     ///
     /// # Arguments
@@ -159,6 +355,25 @@ impl FileProcessor {
             }
         };
 
+        // The extension of the output files depends on the output container.
+        let extension = profile
+            .processing_params
+            .misc
+            .output_container
+            .unwrap_or_default()
+            .extension();
+
+        // The separator is added after the title has been sanitized, so it won't be stripped.
+        let separator = profile
+            .index_separator
+            .as_deref()
+            .unwrap_or(DEFAULT_INDEX_SEPARATOR);
+
+        // An index will only be added to the file names if a start index was specified.
+        let pad_width = profile
+            .start_from
+            .map(|_| profile.index_pad_width.unwrap_or_default());
+
         // Create a local copy of the substitution instance.
         let mut substitutions = profile.substitutions.clone();
 
@@ -181,20 +396,30 @@ impl FileProcessor {
                 break;
             }
 
+            // Skip empty lines and comment lines, and remove any trailing comments.
+            let title = match FileProcessor::strip_comments(&line) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            // An explicit index may be given for this entry, in place of the running index.
+            let (entry_index, title) = FileProcessor::split_index_override(&title);
+            let entry_index = entry_index.unwrap_or(index);
+
             // Sanitize the title of the media file based on the supplied
             // substitution parameters.
-            let sanitized = substitutions.apply(&line);
-
-            // Skip empty lines and comment lines.
-            if sanitized.is_empty() || sanitized.starts_with('#') {
+            let sanitized = substitutions.apply(title);
+            if sanitized.is_empty() {
                 continue;
             }
 
             // Handle the number padding, if required.
             let file_name = FileProcessor::file_name_from_padded_index(
                 &sanitized,
-                index,
-                profile.index_pad_type,
+                entry_index,
+                pad_width,
+                separator,
+                extension,
             );
 
             // Add the file output path to the vector.
@@ -204,7 +429,11 @@ impl FileProcessor {
             // Add the title to the vector.
             self.titles.push(sanitized.to_string());
 
-            // Increment the index counter.
+            // Add the padded index to the vector.
+            self.indices
+                .push(FileProcessor::padded_index(entry_index, pad_width));
+
+            // Increment the index counter. Any explicit index does not affect the subsequent entries.
             index += 1;
         }
 
@@ -221,6 +450,11 @@ impl FileProcessor {
             false,
         );
 
+        // Nothing should be remuxed when the profile is only being validated.
+        if profile.processing_params.misc.validate_only {
+            return;
+        }
+
         if let Some(b) = &profile.processing_params.misc.pre_mux_media_files {
             if *b {
                 // Remux certain other media files to allow them to be automatically handled.
@@ -229,58 +463,194 @@ impl FileProcessor {
         }
     }
 
-    /// Build a filename from a name, an index (optional) and a pad type (optional).
+    /// Remove any comments from a line of the output names file.
+    ///
+    /// A line whose first non-whitespace character is a `#` is a comment line. A `#` that is surrounded
+    /// by whitespace, or that ends the line, begins a trailing comment. A `\#` is always treated as a
+    /// literal `#`, allowing titles to start with or contain one.
     ///
     /// # Arguments
     ///
-    /// * `name` - The name of the file.
-    /// * `index` - The index of the file, if applicable.
-    /// * `pad_type` - An option containing the [`PadType`] to be applied to the index.
+    /// * `line` - The line of the output names file.
     ///
     /// # Returns
     ///
-    /// A String giving the new output file name.
-    fn file_name_from_padded_index(name: &str, index: usize, pad_type: Option<PadType>) -> String {
-        let mut str = match pad_type {
-            Some(PadType::One) => {
-                format!("{index} – {name}")
-            }
-            Some(PadType::Ten) => {
-                format!("{index:02} – {name}")
+    /// An option containing the title with any comments removed, or `None` if the line has no title.
+    fn strip_comments(line: &str) -> Option<String> {
+        if line.trim_start().starts_with('#') {
+            return None;
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut title = String::with_capacity(line.len());
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' if chars.get(i + 1) == Some(&'#') => {
+                    title.push('#');
+                    i += 1;
+                }
+                '#' if i > 0
+                    && chars[i - 1].is_whitespace()
+                    && chars.get(i + 1).is_none_or(|c| c.is_whitespace()) =>
+                {
+                    break;
+                }
+                c => title.push(c),
             }
-            Some(PadType::Hundred) => {
-                format!("{index:03} – {name}")
+
+            i += 1;
+        }
+
+        let title = title.trim();
+        if title.is_empty() {
+            None
+        } else {
+            Some(title.to_string())
+        }
+    }
+
+    /// Split an explicit index prefix, such as `[12] Title`, from a line of the output names file.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the output names file.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the explicit index, if one was specified, and the remainder of the line.
+    fn split_index_override(line: &str) -> (Option<usize>, &str) {
+        let split = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(index, title)| Some((index.parse().ok()?, title.trim_start())));
+
+        match split {
+            Some((index, title)) if !title.is_empty() => (Some(index), title),
+            _ => (None, line),
+        }
+    }
+
+    /// Check the output file list for any paths that occur more than once, handling them
+    /// as specified by the [`InputProfile`].
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The [`InputProfile`] specified when running the program.
+    ///
+    /// # Returns
+    ///
+    /// True if there are no remaining collisions, false otherwise.
+    fn resolve_output_collisions(&mut self, profile: &InputProfile) -> bool {
+        let mode = profile.output_collision_mode.unwrap_or(CollisionMode::Fail);
+
+        // File paths are compared case-insensitively, as they would be on NTFS.
+        let mut seen = HashSet::new();
+        let mut collisions = Vec::new();
+        for i in 0..self.output_paths.len() {
+            if seen.insert(self.output_paths[i].to_lowercase()) {
+                continue;
             }
-            Some(PadType::Thousand) => {
-                format!("{index:04} – {name}")
+
+            match mode {
+                CollisionMode::Fail => collisions.push(self.output_paths[i].clone()),
+                CollisionMode::Suffix => {
+                    let base = utils::swap_file_extension(&self.output_paths[i], "");
+                    let ext = utils::get_file_extension(&self.output_paths[i]).unwrap_or_default();
+
+                    let mut suffix = 2;
+                    let mut path = format!("{base} ({suffix}).{ext}");
+                    while !seen.insert(path.to_lowercase()) {
+                        suffix += 1;
+                        path = format!("{base} ({suffix}).{ext}");
+                    }
+
+                    logger::log(
+                        format!(
+                            "Output path \"{}\" is a duplicate and will be renamed to \"{path}\".",
+                            self.output_paths[i]
+                        ),
+                        true,
+                    );
+                    self.output_paths[i] = path;
+                }
             }
+        }
+
+        if collisions.is_empty() {
+            return true;
+        }
+
+        logger::log(
+            "The following output paths occur more than once in the output file list:",
+            true,
+        );
+        for path in &collisions {
+            logger::log(format!("\t{path}"), true);
+        }
+
+        false
+    }
+
+    /// Build a filename from a name, an index and a pad width (optional).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the file.
+    /// * `index` - The index of the file, if applicable.
+    /// * `pad_width` - An option containing the width to which the index should be padded,
+    ///   or `None` if no index should be added.
+    /// * `separator` - The separator to be placed between the index and the name.
+    /// * `extension` - The extension of the output file.
+    ///
+    /// # Returns
+    ///
+    /// A String giving the new output file name.
+    fn file_name_from_padded_index(
+        name: &str,
+        index: usize,
+        pad_width: Option<usize>,
+        separator: &str,
+        extension: &str,
+    ) -> String {
+        let mut str = match FileProcessor::padded_index(index, pad_width) {
+            Some(padded) => format!("{padded}{separator}{name}"),
             None => name.to_string(),
         };
 
-        str.push_str(".mkv");
+        str.push('.');
+        str.push_str(extension);
         str
     }
 
-    /// Filter a [`DirEntry`] based on whether it is a file, and has a specific extension.
+    /// Pad an index with leading zeros to a given width.
     ///
     /// # Arguments
     ///
-    /// * `entry` - A reference to the [`DirEntry`] object.
+    /// * `index` - The index of the file.
+    /// * `pad_width` - An option containing the width to which the index should be padded.
+    ///   A width of zero will leave the index unpadded.
     ///
     /// # Returns
     ///
-    /// A String giving the path to the file, if its extension is within the valid extensions list.
-    fn filter_by_file_extension(entry: Result<DirEntry, Error>, exts: &[&str]) -> Option<String> {
-        // Eliminate invalid entries.
-        let dir_entry = match entry {
-            Ok(de) => de,
-            Err(_) => {
-                return None;
-            }
-        };
+    /// A String giving the padded index, if a pad width was specified.
+    fn padded_index(index: usize, pad_width: Option<usize>) -> Option<String> {
+        let width = pad_width?;
+        Some(format!("{index:0width$}"))
+    }
 
+    /// Filter a path based on whether it is a file, and has a specific extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A reference to the path.
+    /// * `exts` - A slice of the valid extensions.
+    ///
+    /// # Returns
+    ///
+    /// A String giving the path to the file, if its extension is within the valid extensions list.
+    fn filter_by_file_extension<S: AsRef<str>>(path: &Path, exts: &[S]) -> Option<String> {
         // We are only interested in files.
-        let path = dir_entry.path();
         if !path.is_file() {
             return None;
         }
@@ -294,7 +664,7 @@ impl FileProcessor {
             .to_string_lossy()
             .to_string();
 
-        if exts.contains(&extension.as_str()) {
+        if exts.iter().any(|e| e.as_ref() == extension) {
             Some(path.display().to_string())
         } else {
             None
@@ -308,6 +678,11 @@ impl FileProcessor {
     /// * `path` - The path to the file.
     /// * `params` - The [`UnifiedParams`] to be used while processing the media files.
     fn maybe_delete_original_file(path: &str, params: &UnifiedParams) {
+        // No files should be deleted during a dry run.
+        if params.misc.dry_run {
+            return;
+        }
+
         match params.misc.remove_original_file {
             Some(DeletionOptions::Delete) => {
                 logger::log_inline("Attempting to delete original media file... ", false);
@@ -329,23 +704,6 @@ impl FileProcessor {
         }
     }
 
-    /// Handle the removal of the the original media file, if remuxing has taken place.
-    ///
-    /// # Arguments
-    ///
-    /// * `params` - The [`UnifiedParams`] to be used while processing the media files.
-    fn maybe_shutdown(params: &UnifiedParams) {
-        // Shutdown the computer after processing, if required.
-        if let Some(b) = params.misc.shutdown_upon_completion {
-            if b {
-                match system_shutdown::shutdown() {
-                    Ok(_) => logger::log("Attempting to shutdown down the computer...", true),
-                    Err(e) => logger::log(format!("Failed to shutdown the computer: {e}"), true),
-                }
-            }
-        }
-    }
-
     /// Run a pre-processing remux on certain media files within the input directory
     /// to permit them to be correctly handled by the main remuxing system.
     ///
@@ -358,28 +716,43 @@ impl FileProcessor {
             false,
         );
 
-        // Build the list of input file paths.
-        let read = fs::read_dir(&profile.input_dir);
-        assert!(
-            read.is_ok(),
-            "Failed to read input files directory: {read:?}"
-        );
+        // The extensions specified by the profile take precedence over the defaults.
+        let extensions: Vec<String> = match &profile.processing_params.misc.pre_mux_extensions {
+            Some(exts) if !exts.is_empty() => exts.iter().map(|e| e.to_lowercase()).collect(),
+            _ => VALID_REMUX_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+        };
 
         // Add all of the matching files into the file list.
-        for path in read
-            .unwrap()
-            .filter_map(|p| FileProcessor::filter_by_file_extension(p, &VALID_REMUX_EXTENSIONS))
+        for path in FileProcessor::walk_input_dir(profile)
+            .iter()
+            .filter_map(|p| FileProcessor::filter_by_file_extension(p, &extensions))
         {
+            let out_path = utils::swap_file_extension(&path, "mkv");
+
+            // Avoid overwriting a file that has already been remuxed.
+            if Path::new(&out_path).exists() {
+                logger::log(
+                    format!("File \"{path}\" has already been remuxed and will be skipped."),
+                    false,
+                );
+                continue;
+            }
+
             logger::log(
                 format!("File \"{path}\" is a valid remuxing target and will be remuxed..."),
                 false,
             );
 
-            let out_path = utils::swap_file_extension(&path, "mkv");
-            converters::remux_media_file(&path, &out_path);
+            let dry_run = profile.processing_params.misc.dry_run;
+            converters::remux_media_file(&path, &out_path, &profile.processing_params.misc);
 
             // Delete the original file, if required.
-            MediaFile::delete_path(&path, &profile.processing_params.misc.remove_original_file);
+            if !dry_run {
+                MediaFile::delete_path(&path, &profile.processing_params.misc.remove_original_file);
+            }
         }
     }
 
@@ -388,19 +761,53 @@ impl FileProcessor {
     /// # Arguments
     ///
     /// * `params` - The [`UnifiedParams`] to be used while processing the media file.
-    pub fn process(&self, params: &UnifiedParams) {
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if every media file was successfully processed, false otherwise.
+    pub fn process(&self, params: &UnifiedParams) -> bool {
         logger::section("Setup", false);
 
+        // Remove any temporary directories left behind by a previous run, if required.
+        if params.misc.clean_temp_on_start.unwrap_or(false) && !params.misc.dry_run {
+            FileProcessor::clean_temp_directory();
+        }
+
         let now = Instant::now();
 
-        // Process the data from each of the media files.
-        let mut media: Vec<MediaFile> = self
-            .input_paths
-            .iter()
-            .filter_map(|p| MediaFile::from_path(p))
-            .collect();
+        // Process the data from each of the media files, noting any that could not be read.
+        let mut media = Vec::with_capacity(self.input_paths.len());
+        let mut dropped = Vec::new();
+        for path in &self.input_paths {
+            match MediaFile::from_path(path, Some(params)) {
+                Ok(m) => media.push(m),
+                Err(reason) => dropped.push((path, reason)),
+            }
+        }
 
         logger::log("", false);
+
+        // The media files must remain aligned with the output paths and titles, so
+        // processing can't continue if any of the files could not be read.
+        if !dropped.is_empty() {
+            logger::log(
+                format!(
+                    "{} of {} input file{} could not be read:",
+                    dropped.len(),
+                    self.input_paths.len(),
+                    if self.input_paths.len() != 1 { "s" } else { "" }
+                ),
+                true,
+            );
+            for (path, reason) in &dropped {
+                logger::log(format!("\t{path}: {reason}"), true);
+            }
+            logger::log(
+                "The input and output file lists would no longer match, so processing has been aborted.",
+                true,
+            );
+            return false;
+        }
         logger::log(
             format!(
                 "Setup complete, in {}.",
@@ -411,43 +818,284 @@ impl FileProcessor {
 
         logger::section("File Processing", true);
 
-        // Process each media file.
-        let mut success = true;
-        for (i, m) in &mut media.iter_mut().enumerate() {
-            logger::subsection(
-                format!("File {} of {}", i + 1, self.input_paths.len()),
+        // Process each media file, either sequentially or concurrently.
+        let progress = Progress::new(media.len());
+        let max_parallel = params.misc.max_parallel_files.unwrap_or(1);
+        let reports = if max_parallel > 1 {
+            self.process_parallel(&mut media, params, max_parallel, &progress)
+        } else {
+            self.process_sequential(&mut media, params, &progress)
+        };
+        progress.finish();
+        let failures = reports.iter().filter(|r| !r.success).count();
+        let skipped = media.len() - reports.len();
+
+        logger::section("", true);
+        if failures == 0 && skipped == 0 {
+            logger::log("All files have been successfully processed!", true);
+        } else {
+            logger::log(
+                format!(
+                    "{} of {} file{} successfully processed, {failures} failed and {skipped} skipped.",
+                    reports.len() - failures,
+                    media.len(),
+                    if media.len() != 1 { "s were" } else { " was" }
+                ),
                 true,
             );
 
-            let start = Instant::now();
-            if !m.process(&self.output_paths[i], &self.titles[i], params) {
-                logger::log("Processing failed.", true);
-                success = false;
-                break;
+            logger::log_level(Level::Error, "The following files could not be processed:");
+            for report in reports.iter().filter(|r| !r.success) {
+                logger::log_level(
+                    Level::Error,
+                    format!(
+                        "\t{}: {}",
+                        report.input_path,
+                        report.error.as_deref().unwrap_or_default()
+                    ),
+                );
             }
+        }
+
+        // Write the batch report, if needed.
+        if let Some(path) = &params.misc.report_path {
+            if !params.misc.dry_run {
+                report::write_report(path, &reports);
+            }
+        }
+
+        failures == 0
+    }
+
+    /// Remove any stale temporary directories left behind by a previous run.
+    /// Only directories matching the naming scheme of the per-file temporary directories will be removed.
+    fn clean_temp_directory() {
+        let entries = match fs::read_dir(paths::temp_dir()) {
+            Ok(e) => e,
+            Err(_) => return,
+        };
 
+        let mut removed = 0;
+        for entry in entries.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_temp_dir = !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_digit())
+                && entry.file_type().is_ok_and(|t| t.is_dir());
+            if !is_temp_dir {
+                continue;
+            }
+
+            match fs::remove_dir_all(entry.path()) {
+                Ok(_) => removed += 1,
+                Err(e) => logger::log(
+                    format!("Failed to remove the stale temporary directory '{name}': {e}"),
+                    true,
+                ),
+            }
+        }
+
+        if removed > 0 {
             logger::log(
                 format!(
-                    "Processing complete, in {}.",
-                    utils::format_duration(start.elapsed().as_secs())
+                    "Removed {removed} stale temporary director{}.",
+                    if removed != 1 { "ies" } else { "y" }
                 ),
-                true,
+                false,
             );
-
-            FileProcessor::maybe_delete_original_file(&self.input_paths[i], params);
         }
+    }
 
-        logger::section("", true);
-        if success {
-            logger::log("All files have been successfully processed!", true);
-        } else {
-            logger::log(
-                "One or more errors occurred and the files could not be processed.",
-                true,
+    /// Process a single media file.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the media file within the input file list.
+    /// * `media` - The [`MediaFile`] to be processed.
+    /// * `params` - The [`UnifiedParams`] to be used while processing the media file.
+    ///
+    /// # Returns
+    ///
+    /// A [`FileReport`] describing the outcome of the processing.
+    fn process_file(
+        &self,
+        index: usize,
+        media: &mut MediaFile,
+        params: &UnifiedParams,
+    ) -> FileReport {
+        logger::subsection(
+            format!("File {} of {}", index + 1, self.input_paths.len()),
+            true,
+        );
+
+        let start = Instant::now();
+        media.index.clone_from(&self.indices[index]);
+        if let Err(e) = media.process(&self.output_paths[index], &self.titles[index], params) {
+            logger::log_level(Level::Error, format!("Processing failed: {e}."));
+            return FileReport::new(
+                media,
+                &self.output_paths[index],
+                Some(&e),
+                start.elapsed().as_secs(),
             );
         }
 
-        FileProcessor::maybe_shutdown(params);
+        logger::log(
+            format!(
+                "Processing complete, in {}.",
+                utils::format_duration(start.elapsed().as_secs())
+            ),
+            true,
+        );
+
+        FileProcessor::maybe_delete_original_file(&self.input_paths[index], params);
+
+        FileReport::new(
+            media,
+            &self.output_paths[index],
+            None,
+            start.elapsed().as_secs(),
+        )
+    }
+
+    /// Process the media files concurrently, using a thread pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - The list of [`MediaFile`] instances to be processed.
+    /// * `params` - The [`UnifiedParams`] to be used while processing the media files.
+    /// * `threads` - The maximum number of media files to be processed at once.
+    /// * `progress` - The [`Progress`] indicator for the batch.
+    ///
+    /// # Returns
+    ///
+    /// A list of [`FileReport`] instances, one for each media file.
+    fn process_parallel(
+        &self,
+        media: &mut [MediaFile],
+        params: &UnifiedParams,
+        threads: usize,
+        progress: &Progress,
+    ) -> Vec<FileReport> {
+        let pool = match ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(p) => p,
+            Err(e) => {
+                logger::log(
+                    format!("Failed to create the thread pool, files will be processed sequentially: {e}"),
+                    true,
+                );
+                return self.process_sequential(media, params, progress);
+            }
+        };
+
+        // By default, a failed file is recorded and the processing continues. If stopping
+        // after an error has been requested, any files not yet started will be skipped.
+        let continue_on_error = params.misc.continue_on_error.unwrap_or(true);
+        let aborted = AtomicBool::new(false);
+
+        pool.install(|| {
+            media
+                .par_iter_mut()
+                .enumerate()
+                .filter_map(|(i, m)| {
+                    if aborted.load(Ordering::SeqCst) {
+                        return None;
+                    }
+
+                    // Buffer the output so that the log for each file is kept together.
+                    progress.set_current(&self.titles[i]);
+                    logger::begin_buffering();
+                    let report = self.process_file(i, m, params);
+                    logger::end_buffering();
+                    progress.complete_one(&self.titles[i]);
+
+                    if !report.success && !continue_on_error {
+                        aborted.store(true, Ordering::SeqCst);
+                    }
+
+                    Some(report)
+                })
+                .collect()
+        })
+    }
+
+    /// Process the media files sequentially.
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - The list of [`MediaFile`] instances to be processed.
+    /// * `params` - The [`UnifiedParams`] to be used while processing the media files.
+    /// * `progress` - The [`Progress`] indicator for the batch.
+    ///
+    /// # Returns
+    ///
+    /// A list of [`FileReport`] instances, one for each media file.
+    fn process_sequential(
+        &self,
+        media: &mut [MediaFile],
+        params: &UnifiedParams,
+        progress: &Progress,
+    ) -> Vec<FileReport> {
+        let continue_on_error = params.misc.continue_on_error.unwrap_or(true);
+
+        let mut reports = Vec::with_capacity(media.len());
+        for (i, m) in media.iter_mut().enumerate() {
+            progress.set_current(&self.titles[i]);
+            let report = self.process_file(i, m, params);
+            progress.complete_one(&self.titles[i]);
+
+            // By default, a failure is recorded and the next file is processed. If stopping
+            // after an error has been requested, any remaining files will be skipped.
+            let success = report.success;
+            reports.push(report);
+            if !success && !continue_on_error {
+                break;
+            }
+        }
+
+        reports
+    }
+
+    /// Get the paths within the input directory that match a glob pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The [`InputProfile`] specified when running the program.
+    /// * `pattern` - The glob pattern, relative to the input directory unless it is absolute.
+    fn glob_input_dir(profile: &InputProfile, pattern: &str) -> Vec<PathBuf> {
+        let full_pattern = utils::join_path_segments(&profile.input_dir, &[pattern]);
+
+        match glob::glob(&full_pattern) {
+            Ok(paths) => paths.filter_map(Result::ok).collect(),
+            Err(e) => {
+                logger::log(
+                    format!("The input pattern '{pattern}' was invalid: {e}"),
+                    true,
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Get the paths of the entries within the input directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The [`InputProfile`] specified when running the program.
+    fn walk_input_dir(profile: &InputProfile) -> Vec<PathBuf> {
+        // Only the top level of the directory will be examined, unless recursion was requested.
+        let max_depth = if profile.recursive.unwrap_or(false) {
+            usize::MAX
+        } else {
+            1
+        };
+
+        WalkDir::new(&profile.input_dir)
+            .min_depth(1)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(DirEntry::into_path)
+            .collect()
     }
 
     /// Validate the paths specified by the [`InputProfile`] are valid.
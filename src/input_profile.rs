@@ -1,24 +1,58 @@
 use crate::{
-    conversion_params::unified::{TrackPredicate, UnifiedParams},
-    file_processor::PadType,
+    conversion_params::unified::UnifiedParams,
+    file_processor::{CollisionMode, SortMode},
     logger,
+    media_file::Codec,
     substitutions::Substitutions,
+    utils,
 };
 
-use serde_derive::Deserialize;
+use serde::de::{self, Deserialize, Deserializer};
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct InputProfile {
     /// The path to the directory containing the input media files.
     pub input_dir: String,
+    /// Should the subdirectories of the input directory also be searched for media files?
+    pub recursive: Option<bool>,
+    /// A glob pattern, relative to the input directory, used to select the input media files.
+    /// If specified, this will be used in place of scanning the input directory.
+    pub input_pattern: Option<String>,
+    /// The list of extensions of the input media files that should be processed.
+    /// Any container supported by MkvToolNix may be used, such as mkv, mka, mks or webm.
+    /// If unspecified, only mkv files will be processed.
+    pub input_extensions: Option<Vec<String>>,
+    /// How the input media files should be sorted before being paired with the output names.
+    /// If unspecified, the files will be sorted naturally.
+    pub sort_mode: Option<SortMode>,
+    /// Should the input media files be paired with the output names by their file names, rather than
+    /// by their positions? Any input files or output names without a match will be skipped.
+    pub match_by_name: Option<bool>,
     /// The path to the directory into which the processed files should be saved.
     pub output_dir: String,
     /// The path to the output names file.
     pub output_names_file_path: String,
+    /// The path to the directory in which any temporary files should be created.
+    /// If unspecified, the temporary directory from the paths file will be used.
+    pub temp_dir: Option<String>,
     /// The index that the names should start from.
     pub start_from: Option<usize>,
-    /// The padding that should be applied to the index.
-    pub index_pad_type: Option<PadType>,
+    /// The width to which the index should be padded with leading zeros.
+    /// If unspecified, or zero, the index won't be padded.
+    /// The legacy padding type names, such as "Hundred", are also accepted.
+    #[serde(
+        default,
+        alias = "index_pad_type",
+        deserialize_with = "pad_width_from_value"
+    )]
+    pub index_pad_width: Option<usize>,
+    /// The separator to be placed between the index and the name of the output files.
+    /// If unspecified, an en dash surrounded by spaces will be used.
+    pub index_separator: Option<String>,
+    /// How any output files that would share the same path should be handled.
+    /// If unspecified, the processing will be aborted.
+    pub output_collision_mode: Option<CollisionMode>,
     /// Any processing parameters that should be applied to the media file.
     pub processing_params: UnifiedParams,
     /// Substitutions to be applied when sanitizing the file titles.
@@ -44,46 +78,166 @@ impl InputProfile {
     }
 
     pub fn validate_index_params(&self) -> bool {
-        self.start_from.is_some() && self.index_pad_type.is_some()
-            || self.start_from.is_none() && self.index_pad_type.is_none()
+        // A padding width of zero is the same as no padding, and so doesn't require a start index.
+        if self.start_from.is_none() && self.index_pad_width.unwrap_or_default() > 0 {
+            logger::log(
+                "The index padding width can't be specified without a start index.",
+                true,
+            );
+            return false;
+        }
+
+        // The separator isn't sanitized, so it must not be able to alter the output directory.
+        if let Some(separator) = &self.index_separator {
+            if separator.contains(['/', '\\']) {
+                logger::log(
+                    format!(
+                        "The index separator \"{separator}\" must not contain a path separator."
+                    ),
+                    true,
+                );
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn validate_misc_params(&self) -> bool {
+        let misc = &self.processing_params.misc;
+
+        // Validate the output file splitting parameters.
+        if let Some(split) = &misc.split {
+            if !split.is_valid() {
+                logger::log(
+                    format!("The output file split parameter {split:?} is invalid."),
+                    true,
+                );
+                return false;
+            }
+        }
+
+        // Validate the paths of any commands to be run, so that a missing command
+        // is reported before any files are processed.
+        let mut valid = true;
+        for command in misc.run.iter().flatten() {
+            match command.args().first() {
+                Some(path) if utils::file_exists(path) => {}
+                Some(path) => {
+                    logger::log(
+                        format!("The run command path '{path}' for the command {command:?} doesn't exist."),
+                        true,
+                    );
+                    valid = false;
+                }
+                None => {
+                    logger::log(
+                        format!("The run command {command:?} doesn't specify a path."),
+                        true,
+                    );
+                    valid = false;
+                }
+            }
+        }
+
+        // The codecs of any converted audio tracks must be supported by the output container,
+        // so that an incompatible conversion is reported before any files are processed.
+        let container = misc.output_container.unwrap_or_default();
+        let conversions = self.processing_params.audio_tracks.conversion.iter().chain(
+            self.processing_params
+                .track_params
+                .iter()
+                .flatten()
+                .filter_map(|t| t.audio_conversion.as_ref()),
+        );
+        for codec in conversions.filter_map(|c| c.codec.clone()) {
+            let codec = Codec::from(codec);
+            if !container.supports_codec(&codec) {
+                logger::log(
+                    format!("The audio conversion codec '{codec:?}' is not supported by the {container:?} container."),
+                    true,
+                );
+                valid = false;
+            }
+        }
+
+        valid
     }
 
     pub fn validate_filter_params(&self) -> bool {
         let pp = &self.processing_params;
 
         // Validate the audio filtering parameters.
-        let audio_valid = match &pp.audio_tracks.predicate {
-            TrackPredicate::Index(i) => !i.is_empty(),
-            TrackPredicate::Language(l) => !l.is_empty(),
-            TrackPredicate::Title(_) => true,
-            TrackPredicate::None => true,
-        };
+        let audio_valid = pp.audio_tracks.predicate.is_valid();
         if !audio_valid {
             logger::log("Failed to validate the audio filter parameters", true);
         }
 
         // Validate the subtitle filtering parameters.
-        let subtitle_valid = match &pp.subtitle_tracks.predicate {
-            TrackPredicate::Index(i) => !i.is_empty(),
-            TrackPredicate::Language(l) => !l.is_empty(),
-            TrackPredicate::Title(_) => true,
-            TrackPredicate::None => true,
-        };
+        let subtitle_valid = pp.subtitle_tracks.predicate.is_valid();
         if !subtitle_valid {
             logger::log("Failed to validate the subtitle filter parameters", true);
         }
 
         // Validate the video filtering parameters.
-        let video_valid = match &pp.video_tracks.predicate {
-            TrackPredicate::Index(i) => !i.is_empty(),
-            TrackPredicate::Language(l) => !l.is_empty(),
-            TrackPredicate::Title(_) => true,
-            TrackPredicate::None => true,
-        };
+        let video_valid = pp.video_tracks.predicate.is_valid();
         if !video_valid {
-            logger::log("Failed to validate the subtitle filter parameters", true);
+            logger::log("Failed to validate the video filter parameters", true);
         }
 
         audio_valid && subtitle_valid && video_valid
     }
+
+    pub fn validate_substitution_params(&self) -> bool {
+        // Validate the regular expression substitutions, so that an invalid expression or a
+        // reference to a missing capture group is reported before any files are processed.
+        let valid = self.substitutions.is_valid();
+        if !valid {
+            logger::log("Failed to validate the substitution parameters", true);
+        }
+
+        valid
+    }
+}
+
+/// The raw profile value of an index padding width, which may be either a width or a legacy padding type name.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PadWidthValue {
+    Width(usize),
+    Name(String),
+}
+
+/// Deserialize an index padding width, accepting either a width or a legacy padding type name.
+///
+/// # Arguments
+///
+/// * `deserializer` - The deserializer.
+///
+/// # Returns
+///
+/// A result containing the padding width, if one was specified.
+fn pad_width_from_value<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let width = match Option::<PadWidthValue>::deserialize(deserializer)? {
+        Some(PadWidthValue::Width(width)) => width,
+        Some(PadWidthValue::Name(name)) => match name.as_str() {
+            "One" => 1,
+            "Ten" => 2,
+            "Hundred" => 3,
+            "Thousand" => 4,
+            "TenThousand" => 5,
+            "HundredThousand" => 6,
+            _ => {
+                return Err(de::Error::custom(format!(
+                    "invalid index padding '{name}', expected a width or a padding type name"
+                )))
+            }
+        },
+        None => return Ok(None),
+    };
+
+    Ok(Some(width))
 }
@@ -1,13 +1,93 @@
 use crate::paths::PATHS;
 
 use lazy_static::lazy_static;
-use std::fmt::Display;
-use std::{fs::File, io::prelude::*, sync::Mutex};
+use std::fmt::{self, Display};
+use std::{
+    cell::RefCell,
+    env,
+    fs::{File, OpenOptions},
+    io::{self, prelude::*, IsTerminal},
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 lazy_static! {
     pub static ref LOGGER: Mutex<Logger> = Mutex::new(Logger::new());
 }
 
+/// The severity of a log message, ordered from the most to the least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        };
+
+        write!(f, "{str}")
+    }
+}
+
+impl FromStr for Level {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err(()),
+        }
+    }
+}
+
+thread_local! {
+    /// A per-thread buffer, used to keep the output for a single media file together
+    /// when several files are being processed concurrently.
+    static BUFFER: RefCell<Option<Vec<(String, bool)>>> = const { RefCell::new(None) };
+}
+
+/// Start buffering any log messages written by the current thread.
+pub fn begin_buffering() {
+    BUFFER.with(|b| *b.borrow_mut() = Some(Vec::new()));
+}
+
+/// Stop buffering log messages for the current thread, writing any buffered messages in one block.
+pub fn end_buffering() {
+    let entries = take_buffered();
+    let mut logger = LOGGER.lock().unwrap();
+    for (message, console) in entries {
+        logger.log_inline(&message, console);
+    }
+}
+
+/// Replay a list of previously buffered log messages on the current thread.
+///
+/// # Arguments
+///
+/// * `entries` - The buffered messages, as returned by [`take_buffered`].
+pub fn replay(entries: Vec<(String, bool)>) {
+    for (message, console) in entries {
+        write(&message, console);
+    }
+}
+
+/// Stop buffering log messages for the current thread, returning any buffered messages.
+pub fn take_buffered() -> Vec<(String, bool)> {
+    BUFFER.with(|b| b.borrow_mut().take()).unwrap_or_default()
+}
+
 pub fn is_first_section() -> bool {
     LOGGER.lock().unwrap().is_first_section
 }
@@ -24,12 +104,39 @@ pub fn set_is_first_section(first: bool) {
     LOGGER.lock().unwrap().is_first_section = first;
 }
 
+/// Set the least severe level of message that should be logged.
+///
+/// # Arguments
+///
+/// * `level` - The threshold [`Level`]. Any less severe messages will be discarded.
+pub fn set_level(level: Level) {
+    LOGGER.lock().unwrap().level = level;
+}
+
+/// Set the progress indicator that is shown below the console output.
+///
+/// # Arguments
+///
+/// * `progress` - The progress indicator to be shown, or `None` if it should be removed.
+pub fn set_progress(progress: Option<String>) {
+    LOGGER.lock().unwrap().set_progress(progress);
+}
+
+/// Set whether the console output should be restricted to warnings and errors.
+///
+/// # Arguments
+///
+/// * `quiet` - Should messages less severe than [`Level::Warn`] be kept off the console?
+pub fn set_quiet(quiet: bool) {
+    LOGGER.lock().unwrap().quiet = quiet;
+}
+
 pub fn log<S>(message: S, console: bool)
 where
     S: Display,
     S: AsRef<str>,
 {
-    LOGGER.lock().unwrap().log(message.as_ref(), console);
+    write_level(Level::Info, &format!("{message}\r\n"), console);
 }
 
 pub fn log_inline<S>(message: S, console: bool)
@@ -37,7 +144,26 @@ where
     S: Display,
     S: AsRef<str>,
 {
-    LOGGER.lock().unwrap().log_inline(message.as_ref(), console);
+    write_level(Level::Info, message.as_ref(), console);
+}
+
+/// Log a message with a given severity level, prefixed by the name of the level.
+/// Warnings and errors will also be written to the console.
+///
+/// # Arguments
+///
+/// * `level` - The [`Level`] of the message.
+/// * `message` - The message to be logged.
+pub fn log_level<S>(level: Level, message: S)
+where
+    S: Display,
+    S: AsRef<str>,
+{
+    write_level(
+        level,
+        &format!("[{level}] {message}\r\n"),
+        level <= Level::Warn,
+    );
 }
 
 pub fn log_output_lines(output: &str, console: bool) {
@@ -47,7 +173,7 @@ pub fn log_output_lines(output: &str, console: bool) {
             continue;
         }
 
-        LOGGER.lock().unwrap().log(&format!(">\t{line}"), console);
+        log(format!(">\t{line}"), console);
     }
 }
 
@@ -73,11 +199,80 @@ where
     log(format!("[{title}]"), console);
 }
 
-#[allow(unused)]
+/// Write a message with a given severity level, applying the level threshold and quiet mode.
+///
+/// # Arguments
+///
+/// * `level` - The [`Level`] of the message.
+/// * `message` - The message to be written.
+/// * `console` - Should the message also be written to the console?
+fn write_level(level: Level, message: &str, console: bool) {
+    let (threshold, quiet) = {
+        let logger = LOGGER.lock().unwrap();
+        (logger.level, logger.quiet)
+    };
+
+    if level > threshold {
+        return;
+    }
+
+    write(message, is_console_message(level, console, quiet));
+}
+
+/// Check whether a message should be written to the console.
+/// In quiet mode, only warnings and errors will be written to the console.
+///
+/// # Arguments
+///
+/// * `level` - The [`Level`] of the message.
+/// * `console` - Was the message intended to be written to the console?
+/// * `quiet` - Is quiet mode enabled?
+///
+/// # Returns
+///
+/// A boolean, true if the message should be written to the console, false otherwise.
+fn is_console_message(level: Level, console: bool, quiet: bool) -> bool {
+    console && !(quiet && level > Level::Warn)
+}
+
+/// Write a message to the buffer of the current thread, if buffering is enabled, or to the logger otherwise.
+///
+/// # Arguments
+///
+/// * `message` - The message to be written.
+/// * `console` - Should the message also be written to the console?
+fn write(message: &str, console: bool) {
+    let buffered = BUFFER.with(|b| match b.borrow_mut().as_mut() {
+        Some(buffer) => {
+            buffer.push((message.to_string(), console));
+            true
+        }
+        None => false,
+    });
+
+    if !buffered {
+        LOGGER.lock().unwrap().log_inline(message, console);
+    }
+}
+
 pub struct Logger {
     pub enabled: bool,
     pub is_first_section: bool,
+    pub level: Level,
+    pub quiet: bool,
     file: Option<File>,
+    /// Is the next message written to the file the start of a new line?
+    at_line_start: bool,
+    /// Should a session separator be written before the next message written to the file?
+    needs_separator: bool,
+    /// Should warnings and errors be colored when written to the console?
+    use_color: bool,
+    /// Is the console output attached to a terminal?
+    is_terminal: bool,
+    /// Is the next message written to the console the start of a new line?
+    console_at_line_start: bool,
+    /// The progress indicator that is shown below the console output, if any.
+    progress: Option<String>,
 }
 
 impl Logger {
@@ -85,34 +280,265 @@ impl Logger {
         Self {
             enabled: false,
             is_first_section: true,
-            file: match File::create(&PATHS.log) {
+            level: Level::Info,
+            quiet: false,
+            at_line_start: true,
+            file: match Logger::open_file() {
                 Err(e) => {
                     eprintln!("failed to open log file {}: {}", PATHS.log, e);
                     None
                 }
                 Ok(f) => Some(f),
             },
+            needs_separator: PATHS.log_append.unwrap_or(false),
+            use_color: env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+            is_terminal: io::stdout().is_terminal(),
+            console_at_line_start: true,
+            progress: None,
         }
     }
 
-    pub fn log(&mut self, message: &str, console: bool) {
-        self.log_inline(&format!("{message}\r\n"), console);
+    /// Open the log file, either truncating or appending to it as specified in the paths file.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the opened [`File`], or the error if the file could not be opened.
+    fn open_file() -> io::Result<File> {
+        if PATHS.log_append.unwrap_or(false) {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&PATHS.log)
+        } else {
+            File::create(&PATHS.log)
+        }
     }
 
     pub fn log_inline(&mut self, message: &str, console: bool) {
         if console {
-            print!("{message}");
+            // The progress indicator must be cleared before a new line can be written.
+            let show_progress = self.progress.is_some() && !message.is_empty();
+            if show_progress && self.console_at_line_start {
+                print!("\r\x1b[2K");
+            }
+
+            match self.console_color(message) {
+                Some(color) => {
+                    // Keep the line ending outside of the colored region.
+                    let text = message.trim_end_matches(['\r', '\n']);
+                    let ending = &message[text.len()..];
+                    print!("\x1b[{color}m{text}\x1b[0m{ending}");
+                }
+                None => print!("{message}"),
+            }
+
+            // The progress indicator is redrawn once the line is complete.
+            if show_progress {
+                self.console_at_line_start = message.ends_with('\n');
+                if self.console_at_line_start {
+                    self.draw_progress();
+                }
+            }
         }
 
-        #[cfg(feature = "logging")]
-        {
-            if !self.enabled {
-                return;
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(file) = &mut self.file {
+            // Separate the output of this session from any previous sessions in the file.
+            if self.needs_separator {
+                self.needs_separator = false;
+                _ = write!(
+                    file,
+                    "\r\n{:=^1$}\r\n",
+                    format!(" Session started at {} UTC ", Logger::timestamp()),
+                    60
+                );
             }
 
-            if let Some(file) = &mut self.file {
-                _ = write!(file, "{message}");
+            // Each logical line should only be timestamped once, even if written in several parts.
+            if self.at_line_start {
+                _ = write!(file, "[{}] ", Logger::timestamp());
             }
+
+            _ = write!(file, "{message}");
+        }
+
+        if !message.is_empty() {
+            self.at_line_start = message.ends_with('\n');
+        }
+    }
+
+    /// Set the progress indicator that is shown below the console output.
+    /// The progress indicator is only shown if the console output is attached to a terminal.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - The progress indicator to be shown, or `None` if it should be removed.
+    pub fn set_progress(&mut self, progress: Option<String>) {
+        if !self.is_terminal {
+            return;
+        }
+
+        self.progress = progress;
+
+        // The progress indicator can't be drawn midway through a line.
+        if !self.console_at_line_start {
+            return;
+        }
+
+        if self.progress.is_some() {
+            self.draw_progress();
+        } else {
+            print!("\r\x1b[2K");
+            _ = io::stdout().flush();
+        }
+    }
+
+    /// Draw the progress indicator, replacing the current console line.
+    fn draw_progress(&self) {
+        if let Some(progress) = &self.progress {
+            print!("\r\x1b[2K{progress}");
+            _ = io::stdout().flush();
         }
     }
+
+    /// Get the ANSI color code that should be used when writing a message to the console.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to be written.
+    ///
+    /// # Returns
+    ///
+    /// The ANSI color code, if the message should be colored.
+    fn console_color(&self, message: &str) -> Option<&'static str> {
+        if !self.use_color {
+            return None;
+        }
+
+        if message.starts_with("[ERROR]") {
+            Some("31")
+        } else if message.starts_with("[WARN]") {
+            Some("33")
+        } else {
+            None
+        }
+    }
+
+    /// Get the current UTC time of day, in the HH:MM:SS format.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the formatted timestamp.
+    fn timestamp() -> String {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            % 86400;
+
+        format!(
+            "{:02}:{:02}:{:02}",
+            secs / 3600,
+            (secs % 3600) / 60,
+            secs % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    /// Create a logger that writes to a new file, without touching the shared log file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the log file, which is made unique to the test process.
+    fn file_logger(name: &str) -> (Logger, PathBuf) {
+        let path = env::temp_dir()
+            .join("bulk-mkv-manipulation-tests")
+            .join(format!("{}-{name}", std::process::id()));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let logger = Logger {
+            enabled: false,
+            is_first_section: true,
+            level: Level::Info,
+            quiet: false,
+            file: Some(File::create(&path).unwrap()),
+            at_line_start: true,
+            needs_separator: false,
+            use_color: false,
+            is_terminal: false,
+            console_at_line_start: true,
+            progress: None,
+        };
+
+        (logger, path)
+    }
+
+    #[test]
+    fn errors_reach_the_console_in_quiet_mode() {
+        assert!(is_console_message(Level::Error, true, true));
+        assert!(is_console_message(Level::Warn, true, true));
+        assert!(!is_console_message(Level::Info, true, true));
+        assert!(is_console_message(Level::Info, true, false));
+        assert!(!is_console_message(Level::Error, false, false));
+
+        // Errors are always intended for the console, regardless of the caller.
+        begin_buffering();
+        log_level(Level::Error, "an error");
+        let entries = take_buffered();
+        assert_eq!(entries, [("[ERROR] an error\r\n".to_string(), true)]);
+    }
+
+    #[test]
+    fn enabled_logger_writes_to_the_log_file() {
+        // The --logging flag enables the logger in the same way.
+        let (mut logger, path) = file_logger("logging.log");
+        logger.enabled = true;
+        logger.log_inline("written\r\n", false);
+
+        assert!(fs::read_to_string(&path).unwrap().contains("] written"));
+    }
+
+    #[test]
+    fn disabled_logger_does_not_write_to_the_log_file() {
+        let (mut logger, path) = file_logger("disabled.log");
+        logger.log_inline("not written\r\n", false);
+
+        assert!(fs::read_to_string(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn enabled_logger_timestamps_each_line_once() {
+        let (mut logger, path) = file_logger("enabled.log");
+        logger.enabled = true;
+        logger.log_inline("first ", false);
+        logger.log_inline("part\r\n", false);
+        logger.log_inline("second\r\n", false);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] first part"));
+        assert!(lines[1].ends_with("] second"));
+    }
+
+    #[test]
+    fn level_messages_are_colored_on_the_console() {
+        let (mut logger, _) = file_logger("color.log");
+        logger.use_color = true;
+
+        // Failures are logged with a level, and so are colored by the level prefix.
+        let message = |level: Level| format!("[{level}] Processing failed\r\n");
+        assert_eq!(logger.console_color(&message(Level::Error)), Some("31"));
+        assert_eq!(logger.console_color(&message(Level::Warn)), Some("33"));
+        assert_eq!(logger.console_color(&message(Level::Info)), None);
+        assert_eq!(logger.console_color("Processing failed\r\n"), None);
+    }
 }
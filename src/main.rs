@@ -6,61 +6,297 @@ mod logger;
 mod media_file;
 mod mkvtoolnix;
 mod paths;
+mod progress;
+mod report;
 mod substitutions;
 mod utils;
 
 use file_processor::FileProcessor;
 use input_profile::InputProfile;
-use std::{env, fs};
+use logger::Level;
+use media_file::MediaFile;
+use std::{env, fs, process};
+
+/// The options specified on the command line that apply to every profile.
+struct CliOptions {
+    /// Should the commands be logged rather than executed?
+    dry_run: bool,
+    /// Should the profiles be validated without processing any files?
+    validate_only: bool,
+    /// The index from which the output file names should start, overriding the profile.
+    start_from: Option<usize>,
+    /// The directory in which any temporary files should be created, overriding the profile.
+    temp_dir: Option<String>,
+    /// Should the effective profiles be printed without processing any files?
+    print_config: bool,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() == 3 {
-        // Do we need to enable logging?
-        if args[2].to_lowercase() == "--logging" {
-            logger::set_enabled(true);
+    // Parse any optional flags, and the paths to the profiles.
+    let mut options = CliOptions {
+        dry_run: false,
+        validate_only: false,
+        start_from: None,
+        temp_dir: None,
+        print_config: false,
+    };
+    let mut profile_paths = Vec::new();
+    let mut info_path = None;
+    let mut flags = args.iter().skip(1);
+    while let Some(arg) = flags.next() {
+        match arg.to_lowercase().as_str() {
+            // Do we need to enable logging?
+            "--logging" => logger::set_enabled(true),
+            // Should the commands be logged rather than executed?
+            "--dry-run" => options.dry_run = true,
+            // Should the profile be validated without processing any files?
+            "--validate" => options.validate_only = true,
+            // Should the effective profile be printed without processing any files?
+            "--print-config" => options.print_config = true,
+            // Should only warnings and errors be written to the console?
+            "--quiet" => logger::set_quiet(true),
+            // Should the output index start from a different value than the one in the profile?
+            "--start-from" => match flags.next().map(|v| v.parse::<usize>()) {
+                Some(Ok(n)) => options.start_from = Some(n),
+                _ => {
+                    eprintln!("An invalid or missing value was specified for --start-from.");
+                    process::exit(1);
+                }
+            },
+            // Should the temporary files be created in a different directory than the one in the paths file?
+            "--temp-dir" => match flags.next() {
+                Some(dir) => options.temp_dir = Some(dir.clone()),
+                None => {
+                    eprintln!("No value was specified for --temp-dir.");
+                    process::exit(1);
+                }
+            },
+            // Should the tracks of a media file be listed, without processing anything?
+            "--info" => match flags.next() {
+                Some(path) => info_path = Some(path.clone()),
+                None => {
+                    eprintln!("No value was specified for --info.");
+                    process::exit(1);
+                }
+            },
+            arg if arg.starts_with("--log-level=") => {
+                match arg["--log-level=".len()..].parse::<Level>() {
+                    Ok(level) => logger::set_level(level),
+                    Err(_) => {
+                        eprintln!("Invalid log level specified: {arg}");
+                        process::exit(1);
+                    }
+                }
+            }
+            // Any unrecognized flag is most likely a typo, and shouldn't be mistaken for a profile path.
+            arg if arg.starts_with("--") => {
+                eprintln!("Unknown command line flag: {arg}");
+                process::exit(1);
+            }
+            // Anything else is the path to a conversion profile data file.
+            _ => profile_paths.push(arg.clone()),
+        }
+    }
+
+    // List the tracks of the media file and exit, if required.
+    if let Some(path) = info_path {
+        process::exit(if print_file_info(&path) { 0 } else { 1 });
+    }
+
+    if profile_paths.is_empty() {
+        eprintln!("No path to the conversion profile data file was specified.");
+        process::exit(1);
+    }
+
+    // Run each of the profiles in turn.
+    let mut results = Vec::with_capacity(profile_paths.len());
+    let mut shutdown = false;
+    for path in &profile_paths {
+        let (success, wants_shutdown) = run_profile(path, &options);
+        results.push((path, success));
+        shutdown |= wants_shutdown;
+    }
+
+    // Output a summary, if more than one profile was run.
+    if results.len() > 1 {
+        logger::section("Profile Summary", true);
+        for (path, success) in &results {
+            logger::log(
+                format!("{path}: {}", if *success { "succeeded" } else { "FAILED" }),
+                true,
+            );
         }
     }
 
-    logger::section("Initial Setup", false);
+    // Shutdown the computer after processing, if required.
+    // The computer should never be shutdown during a dry run or a validation.
+    if shutdown && !options.dry_run && !options.validate_only {
+        match system_shutdown::shutdown() {
+            Ok(_) => logger::log("Attempting to shutdown down the computer...", true),
+            Err(e) => logger::log_level(
+                Level::Error,
+                format!("Failed to shutdown the computer: {e}"),
+            ),
+        }
+    }
+
+    if results.iter().any(|(_, success)| !success) {
+        process::exit(1);
+    }
+}
+
+/// Print the tracks and attachments of a media file, without processing it.
+///
+/// # Arguments
+///
+/// * `path` - The path to the media file.
+///
+/// # Returns
+///
+/// A boolean, true if the media file could be read, false otherwise.
+fn print_file_info(path: &str) -> bool {
+    match MediaFile::from_path(path, None) {
+        Ok(media) => {
+            media.log_info();
+            true
+        }
+        Err(e) => {
+            logger::log_level(
+                Level::Error,
+                format!("Unable to read the media file {path}: {e}."),
+            );
+            false
+        }
+    }
+}
+
+/// Load, validate and process a single conversion profile.
+///
+/// # Arguments
+///
+/// * `profile_path` - The path to the conversion profile data file.
+/// * `options` - The [`CliOptions`] specified on the command line.
+///
+/// # Returns
+///
+/// A tuple containing a boolean, true if the profile was successfully processed, and
+/// a boolean indicating whether the profile requested a shutdown upon completion.
+fn run_profile(profile_path: &str, options: &CliOptions) -> (bool, bool) {
+    logger::section(format!("Initial Setup: {profile_path}"), false);
 
     // Read and parse the conversion profile data file.
-    assert!(
-        args.len() >= 2,
-        "No path to the conversion profile data file was specified."
-    );
-    let profile_path = &args[1];
-    assert!(
-        utils::file_exists(profile_path),
-        "The path to the conversion profile data file was invalid."
-    );
-
-    let profile_json = fs::read_to_string(profile_path).expect("failed to open profile data file");
-    let profile = serde_json::from_str::<InputProfile>(&profile_json);
-    assert!(
-        profile.is_ok(),
-        "An error occurred while attempting to parse the JSON data: {:?}.",
-        profile.err()
-    );
+    if !utils::file_exists(profile_path) {
+        logger::log_level(
+            Level::Error,
+            format!("The path to the conversion profile data file was invalid: {profile_path}"),
+        );
+        return (false, false);
+    }
+
+    let profile_json = match fs::read_to_string(profile_path) {
+        Ok(json) => json,
+        Err(e) => {
+            logger::log_level(
+                Level::Error,
+                format!("Failed to open the profile data file {profile_path}: {e}"),
+            );
+            return (false, false);
+        }
+    };
+    let mut profile = match serde_json::from_str::<InputProfile>(&profile_json) {
+        Ok(profile) => profile,
+        Err(e) => {
+            logger::log_level(
+                Level::Error,
+                format!("The profile data file {profile_path} is not valid: {e}."),
+            );
+            return (false, false);
+        }
+    };
 
     logger::log("Attempting to validate filter parameters...", false);
 
-    let mut profile = profile.unwrap();
+    profile.processing_params.misc.dry_run = options.dry_run;
+    profile.processing_params.misc.validate_only = options.validate_only;
+
+    // Override the output start index, if one was specified on the command line.
+    if options.start_from.is_some() {
+        profile.start_from = options.start_from;
+    }
+
+    // Override the temporary directory, if one was specified on the command line.
+    if options.temp_dir.is_some() {
+        profile.temp_dir.clone_from(&options.temp_dir);
+    }
+
+    // Print the profile, with any defaults and command line overrides applied, if required.
+    if options.print_config {
+        return match serde_json::to_string_pretty(&profile) {
+            Ok(json) => {
+                println!("{json}");
+                (true, false)
+            }
+            Err(e) => {
+                logger::log_level(
+                    Level::Error,
+                    format!("Failed to serialize the profile data file {profile_path}: {e}."),
+                );
+                (false, false)
+            }
+        };
+    }
+
+    // Use a separate temporary directory for this run, if one was specified.
+    // This allows several instances to be run concurrently without sharing a temporary directory.
+    let temp_dir = profile.temp_dir.clone();
+    paths::set_temp_dir(temp_dir.clone());
+
+    let shutdown = profile
+        .processing_params
+        .misc
+        .shutdown_upon_completion
+        .unwrap_or(false);
+
+    // A validation must not have any side effects, so the temporary directory is only created afterwards.
+    if options.validate_only {
+        return (validate_profile(&mut profile), shutdown);
+    }
+
+    if let Some(dir) = &temp_dir {
+        if !options.dry_run && fs::create_dir_all(dir).is_err() {
+            logger::log_level(
+                Level::Error,
+                format!("Failed to create the temporary directory: {dir}"),
+            );
+            return (false, false);
+        }
+    }
 
     // Validate the index processing parameters.
     if !profile.validate_index_params() {
-        return;
+        return (false, shutdown);
     }
 
     // Initialize any regular expression filters that may be present.
     if !profile.initialize_filters() {
-        return;
+        return (false, shutdown);
     }
 
     // Validate the track filter parameters.
     if !profile.validate_filter_params() {
-        return;
+        return (false, shutdown);
+    }
+
+    // Validate the miscellaneous parameters.
+    if !profile.validate_misc_params() {
+        return (false, shutdown);
+    }
+
+    // Validate the title substitution parameters.
+    if !profile.validate_substitution_params() {
+        return (false, shutdown);
     }
 
     logger::log("All parameters successfully validated.", false);
@@ -68,9 +304,57 @@ fn main() {
     // Create the file processor instance.
     let file_processor = match FileProcessor::new(&profile) {
         Some(p) => p,
-        None => return,
+        None => return (false, shutdown),
     };
 
     // Run the converter.
-    file_processor.process(&profile.processing_params);
+    (file_processor.process(&profile.processing_params), shutdown)
+}
+
+/// Run each of the validation checks against a profile, without processing any files.
+///
+/// # Arguments
+///
+/// * `profile` - The [`InputProfile`] to be validated.
+///
+/// # Returns
+///
+/// A boolean, true if every validation check passed, false otherwise.
+fn validate_profile(profile: &mut InputProfile) -> bool {
+    logger::section("Profile Validation", true);
+
+    let checks = [
+        ("Index parameters", profile.validate_index_params()),
+        ("Regular expression filters", profile.initialize_filters()),
+        ("Track filter parameters", profile.validate_filter_params()),
+        ("Miscellaneous parameters", profile.validate_misc_params()),
+        (
+            "Substitution parameters",
+            profile.validate_substitution_params(),
+        ),
+        (
+            "Input and output file lists",
+            FileProcessor::new(profile).is_some(),
+        ),
+    ];
+
+    logger::section("Validation Summary", true);
+    for (name, valid) in &checks {
+        logger::log(
+            format!("{name}: {}", if *valid { "passed" } else { "FAILED" }),
+            true,
+        );
+    }
+
+    let failures = checks.iter().filter(|(_, valid)| !valid).count();
+    if failures == 0 {
+        logger::log("The profile is valid.", true);
+    } else {
+        logger::log_level(
+            Level::Error,
+            format!("{failures} validation check(s) failed."),
+        );
+    }
+
+    failures == 0
 }
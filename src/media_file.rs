@@ -4,39 +4,51 @@ use crate::{
         params_trait::ConversionParams,
         subtitle::SubtitleConvertParams,
         unified::{
-            DeletionOptions, PredicateFilterMatch, ProcessRun, TrackPredicate, UnifiedParams,
+            Container, DeletionOptions, MiscParams, ProcessRun, RetainMode, TrackPredicate,
+            TrackRetention, UnifiedParams,
         },
         video::VideoConvertParams,
     },
-    converters, logger, mkvtoolnix, paths, utils,
+    converters,
+    logger::{self, Level},
+    mkvtoolnix, paths, utils,
 };
 
 use core::fmt;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
 use serde::de::{self, Deserialize, Deserializer, Unexpected};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use std::{
     fs,
     path::Path,
     process::Command,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
 };
 use walkdir::{DirEntry, Error, WalkDir};
 
 /// This will generate sequential thread-global unique IDs for instances of this struct.
 static UNIQUE_ID: AtomicUsize = AtomicUsize::new(0);
 
-/// This will indicate whether the JSON MediaInfo output should be exported to a file.
-const EXPORT_JSON: bool = false;
-
-/// This will indicate whether to output the command line parameters used.
-const DEBUG_PARAMS: bool = false;
+lazy_static! {
+    /// Matches the start and end times within a chapters XML file.
+    static ref CHAPTER_TIME_REGEX: Regex = Regex::new(
+        r"<(ChapterTimeStart|ChapterTimeEnd)>\s*(\d+):(\d{2}):(\d{2})(?:\.(\d{1,9}))?\s*</(?:ChapterTimeStart|ChapterTimeEnd)>"
+    )
+    .unwrap();
+}
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
 pub enum Codec {
     Aac,
     Ac3,
     AdvancedSsa,
+    Av1,
     Dts,
     DvbSubtitle,
     Eac3,
@@ -45,6 +57,7 @@ pub enum Codec {
     H264,
     Hdmv,
     Hevc,
+    Mp2,
     Mp3,
     Opus,
     SubStationAlpha,
@@ -52,8 +65,10 @@ pub enum Codec {
     SubtitleBitmap,
     #[default]
     Unknown,
+    Vorbis,
     Vp8,
     Vp9,
+    WavPack,
     WebVtt,
 }
 
@@ -64,16 +79,20 @@ impl From<AudioCodec> for Codec {
             AudioCodec::AacLibfdk => Codec::Aac,
             AudioCodec::Ac3 => Codec::Ac3,
             AudioCodec::Flac => Codec::Flac,
+            AudioCodec::Mp2 => Codec::Mp2,
             AudioCodec::Mp3Lame => Codec::Mp3,
             AudioCodec::Mp3Shine => Codec::Mp3,
             AudioCodec::Opus => Codec::Opus,
-            AudioCodec::Vorbis => Codec::Opus,
+            AudioCodec::Vorbis => Codec::Vorbis,
+            AudioCodec::WavPack => Codec::WavPack,
         }
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum RunCommandType {
+    /// A command that should be executed prior to the extraction of the tracks from the input MKV file.
+    PreExtract,
     /// A command that should be executed prior converting the tracks using FFMPEG.
     PreConvert,
     /// A command that should be executed prior to the muxing of the output MKV file.
@@ -84,6 +103,68 @@ pub enum RunCommandType {
     PostMux,
 }
 
+/// The reasons for which the processing of a media file may fail.
+#[derive(Debug)]
+pub enum ProcessError {
+    /// A user-specified command failed to run.
+    CommandFailed(RunCommandType),
+    /// The output container does not support the codecs of one or more tracks.
+    UnsupportedCodecs {
+        container: Container,
+        tracks: Vec<(u32, Codec)>,
+    },
+    /// The tracks, attachments or chapters could not be extracted.
+    ExtractionFailed,
+    /// There is insufficient free space within the temporary directory.
+    InsufficientTempSpace,
+    /// One or more of the tracks could not be converted.
+    ConversionFailed,
+    /// The output file could not be muxed.
+    MuxFailed,
+    /// The number of tracks of a given type did not meet the specified target.
+    TrackTargetUnmet {
+        track_type: TrackType,
+        target: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::CommandFailed(run_type) => {
+                write!(f, "a {run_type:?} command failed to run")
+            }
+            ProcessError::UnsupportedCodecs { container, tracks } => {
+                let list: Vec<String> = tracks
+                    .iter()
+                    .map(|(id, codec)| format!("{id} ({codec:?})"))
+                    .collect();
+                write!(
+                    f,
+                    "the {container:?} container does not support the codecs of tracks {}",
+                    list.join(", ")
+                )
+            }
+            ProcessError::ExtractionFailed => write!(f, "the extraction failed"),
+            ProcessError::InsufficientTempSpace => write!(
+                f,
+                "there was insufficient free space within the temporary directory"
+            ),
+            ProcessError::ConversionFailed => write!(f, "the conversion of a track failed"),
+            ProcessError::MuxFailed => write!(f, "the muxing of the output file failed"),
+            ProcessError::TrackTargetUnmet {
+                track_type,
+                target,
+                found,
+            } => write!(
+                f,
+                "requested {target} {track_type} track(s), but {found} were found"
+            ),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
 pub enum DelaySource {
     Container,
@@ -92,7 +173,7 @@ pub enum DelaySource {
     Stream,
 }
 
-#[derive(Clone, Default, Deserialize, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq)]
 pub enum TrackType {
     /// An audio track.
     Audio,
@@ -140,6 +221,14 @@ pub struct MediaFile {
     #[serde(skip)]
     pub output_path: String,
 
+    /// The title of the modified media file.
+    #[serde(skip)]
+    pub title: String,
+
+    /// The padded output index of the modified media file, if indexing is enabled.
+    #[serde(skip)]
+    pub index: Option<String>,
+
     /// The data pertaining to the media file.
     pub media: MediaFileInfo,
 
@@ -151,24 +240,101 @@ pub struct MediaFile {
     #[serde(skip)]
     track_type_counter: HashMap<TrackType, usize>,
 
+    /// The names of the attachments that have been added to the argument list.
+    #[serde(skip)]
+    attachment_names: HashSet<String>,
+
+    /// The sizes and content hashes of the attachments that have been added to the argument list.
+    #[serde(skip)]
+    attachment_hashes: HashSet<(usize, u64)>,
+
     /// The conversion args used for MKV muxing.
     #[serde(skip)]
     muxing_args: Vec<String>,
+
+    /// Any warnings issued by MkvToolNix while processing the media file.
+    #[serde(skip)]
+    warnings: Mutex<Vec<String>>,
+
+    /// Has one of the concurrent extractions failed, such that any extraction not yet started should be skipped?
+    #[serde(skip)]
+    extraction_aborted: AtomicBool,
 }
 
 impl MediaFile {
+    /// Check whether there is sufficient free space within the temporary directory to hold the
+    /// extracted files. The size of the source file is used as a conservative estimate of the space required.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if there is sufficient free space or the free space could not be determined, false otherwise.
+    fn has_sufficient_temp_space(&self) -> bool {
+        let required = match fs::metadata(&self.file_path) {
+            Ok(m) => m.len(),
+            Err(_) => return true,
+        };
+
+        let available = match fs2::available_space(paths::temp_dir()) {
+            Ok(a) => a,
+            Err(e) => {
+                logger::log_level(
+                    Level::Warn,
+                    format!(
+                        "Unable to determine the free space within the temporary directory: {e}"
+                    ),
+                );
+                return true;
+            }
+        };
+
+        if required > available {
+            logger::log_level(
+                Level::Error,
+                format!(
+                    "Insufficient free space within the temporary directory to extract file '{}': {} required, but only {} available. The file will be skipped.",
+                    self.file_path,
+                    utils::format_size(required),
+                    utils::format_size(available)
+                ),
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Log and record any warnings issued while processing the media file.
+    ///
+    /// # Arguments
+    ///
+    /// * `warnings` - The warnings to be recorded.
+    fn add_warnings(&self, warnings: Vec<String>) {
+        for warning in &warnings {
+            logger::log_level(Level::Warn, warning);
+        }
+
+        self.warnings.lock().unwrap().extend(warnings);
+    }
+
+    /// Get any warnings issued by MkvToolNix while processing the media file.
+    pub fn get_warnings(&self) -> Vec<String> {
+        self.warnings.lock().unwrap().clone()
+    }
+
     /// Add an attachment to the argument list.
     ///
     /// # Arguments
     ///
     /// * `path` - A string slice representing the path to the attachment file.
     /// * `accepted_extensions` - A reference to the option containing permitted extensions list. If omitted then all extensions are permitted.
+    /// * `rename_duplicates` - Should the attachment be renamed if an attachment with the same name has already been added?
     fn add_attachment_if_matching(
         &mut self,
         path: &str,
         accepted_extensions: &Option<Vec<String>>,
+        rename_duplicates: bool,
     ) {
-        let file_name = utils::get_file_name(path).unwrap_or_default();
+        let mut file_name = utils::get_file_name(path).unwrap_or_default();
         if file_name.is_empty() {
             return;
         }
@@ -191,14 +357,61 @@ impl MediaFile {
         }
 
         if !utils::file_exists(path) {
-            logger::log(format!("[INFO] Attachment path '{path}' was selected for inclusion but the path couldn't be found. This may be expected if you used external run commands!"), false);
+            logger::log_level(Level::Info, format!("Attachment path '{path}' was selected for inclusion but the path couldn't be found. This may be expected if you used external run commands!"));
             return;
         }
 
+        // Skip any attachment whose contents are identical to an attachment that has already been added.
+        match fs::read(path) {
+            Ok(bytes) => {
+                if !self
+                    .attachment_hashes
+                    .insert((bytes.len(), utils::fnv1a_hash(&bytes)))
+                {
+                    logger::log(
+                        format!("Attachment '{path}' is identical to a previously added attachment and will be skipped."),
+                        false,
+                    );
+                    return;
+                }
+            }
+            Err(e) => {
+                logger::log(format!("Failed to read attachment '{path}': {e:?}"), false);
+            }
+        }
+
+        // Give the attachment a unique name, if required. Attachment names are
+        // compared case-insensitively.
+        if rename_duplicates {
+            let stem = Path::new(&file_name)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let extension = utils::get_file_extension(&file_name)
+                .map(|ext| format!(".{ext}"))
+                .unwrap_or_default();
+
+            let mut counter = 2;
+            while self.attachment_names.contains(&file_name.to_lowercase()) {
+                file_name = format!("{stem}_{counter}{extension}");
+                counter += 1;
+            }
+        }
+        self.attachment_names.insert(file_name.to_lowercase());
+
         // Set the attachment name.
         self.muxing_args.push("--attachment-name".to_string());
         self.muxing_args.push(file_name);
 
+        // Set the attachment MIME type, if it is known.
+        if let Some(mime) =
+            utils::get_file_extension(path).and_then(|ext| utils::get_mime_type(&ext))
+        {
+            self.muxing_args.push("--attachment-mime-type".to_string());
+            self.muxing_args.push(mime.to_string());
+        }
+
         // Set the attachment file path.
         self.muxing_args.push("--attach-file".to_string());
         self.muxing_args.push(path.to_string());
@@ -210,6 +423,11 @@ impl MediaFile {
     ///
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
     fn apply_attachment_mux_params(&mut self, params: &UnifiedParams) {
+        // Add the cover art first, so that it takes precedence over any other cover attachments.
+        if let Some(cover) = &params.attachments.cover_image {
+            self.apply_cover_attachment_mux_params(cover);
+        }
+
         // Apply the internal (extracted) attachment muxing arguments, if needed.
         if params.attachments.import_from_original {
             self.apply_internal_attachment_mux_params(params);
@@ -227,6 +445,52 @@ impl MediaFile {
         }
     }
 
+    /// Apply the parameters needed to attach an image as the cover art of the media file.
+    /// Matroska identifies cover art by the attachment name, so the image will be renamed accordingly.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the cover image.
+    fn apply_cover_attachment_mux_params(&mut self, path: &str) {
+        if !utils::file_exists(path) {
+            logger::log_level(
+                Level::Warn,
+                format!("The cover image '{path}' couldn't be found and will not be attached."),
+            );
+            return;
+        }
+
+        let name = match utils::get_file_extension(path).as_deref() {
+            Some("jpg" | "jpeg") => "cover.jpg",
+            Some("png") => "cover.png",
+            _ => {
+                logger::log_level(
+                    Level::Warn,
+                    format!("The cover image '{path}' must be a JPEG or PNG file, and will not be attached."),
+                );
+                return;
+            }
+        };
+
+        // Record the attachment so that any other attachments are checked against it.
+        if let Ok(bytes) = fs::read(path) {
+            self.attachment_hashes
+                .insert((bytes.len(), utils::fnv1a_hash(&bytes)));
+        }
+        self.attachment_names.insert(name.to_string());
+
+        self.muxing_args.push("--attachment-name".to_string());
+        self.muxing_args.push(name.to_string());
+        if let Some(mime) =
+            utils::get_file_extension(path).and_then(|ext| utils::get_mime_type(&ext))
+        {
+            self.muxing_args.push("--attachment-mime-type".to_string());
+            self.muxing_args.push(mime.to_string());
+        }
+        self.muxing_args.push("--attach-file".to_string());
+        self.muxing_args.push(path.to_string());
+    }
+
     /// Apply default track languages.
     ///
     /// # Arguments
@@ -273,6 +537,7 @@ impl MediaFile {
             self.add_attachment_if_matching(
                 &format!("{}/attachments/{attachment}", temp_path),
                 &params.attachments.import_original_extensions,
+                params.attachments.rename_duplicates.unwrap_or(false),
             );
         }
     }
@@ -290,7 +555,11 @@ impl MediaFile {
             .filter_map(MediaFile::filter_files)
         {
             // If the path is valid, add it to the kept attachments list.
-            self.add_attachment_if_matching(&path, &params.attachments.import_folder_extensions);
+            self.add_attachment_if_matching(
+                &path,
+                &params.attachments.import_folder_extensions,
+                params.attachments.rename_duplicates.unwrap_or(false),
+            );
         }
     }
 
@@ -301,12 +570,34 @@ impl MediaFile {
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
     fn apply_chapters_mux_params(&mut self, params: &UnifiedParams) {
         self.muxing_args.push("--chapter-language".to_string());
-        self.muxing_args.push("en".to_string());
+        self.muxing_args
+            .push(params.chapters.language.clone().unwrap_or("en".to_string()));
+
+        // Was an external chapters file specified?
+        if let Some(path) = &params.chapters.import_from_file {
+            if utils::file_exists(path) {
+                self.muxing_args.push("--chapters".to_string());
+                self.muxing_args.push(path.to_string());
+                return;
+            }
+
+            logger::log(
+                format!("The chapters file \"{path}\" could not be found."),
+                true,
+            );
+        }
 
         // Did we export an existing chapters file?
         let chapters_fp =
             utils::join_path_segments(&self.get_temp_path(), &["chapters", "chapters.xml"]);
         if utils::file_exists(&chapters_fp) {
+            // Shift the chapter times, if required.
+            if let Some(shift) = params.chapters.shift_ms.filter(|s| *s != 0) {
+                if !params.misc.dry_run && !MediaFile::shift_chapters_file(&chapters_fp, shift) {
+                    logger::log("Failed to shift the chapter times.", true);
+                }
+            }
+
             // Yes, include that file.
             self.muxing_args.push("--chapters".to_string());
             self.muxing_args.push(chapters_fp.to_string());
@@ -314,7 +605,13 @@ impl MediaFile {
             // No, we will have to create the chapters from scratch.
             self.muxing_args
                 .push("--generate-chapters-name-template".to_string());
-            self.muxing_args.push("Chapter <NUM:2>".to_string());
+            self.muxing_args.push(
+                params
+                    .chapters
+                    .create_name_template
+                    .clone()
+                    .unwrap_or("Chapter <NUM:2>".to_string()),
+            );
 
             self.muxing_args.push("--generate-chapters".to_string());
 
@@ -329,6 +626,137 @@ impl MediaFile {
         }
     }
 
+    /// Shift the start and end times of every chapter within a chapters file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the chapters XML file.
+    /// * `shift_ms` - The number of milliseconds by which the chapter times should be shifted.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if the chapters file was successfully updated, false otherwise.
+    fn shift_chapters_file(path: &str, shift_ms: i32) -> bool {
+        let xml = match fs::read_to_string(path) {
+            Ok(xml) => xml,
+            Err(e) => {
+                logger::log(format!("Failed to read chapters file: {e:?}"), false);
+                return false;
+            }
+        };
+
+        let shift_ns = shift_ms as i64 * 1_000_000;
+        let shifted = CHAPTER_TIME_REGEX.replace_all(&xml, |caps: &Captures| {
+            let hours: i64 = caps[2].parse().unwrap_or_default();
+            let minutes: i64 = caps[3].parse().unwrap_or_default();
+            let seconds: i64 = caps[4].parse().unwrap_or_default();
+
+            // The fractional part may have fewer than nine digits, so it needs to be padded.
+            let fraction: i64 = format!("{:0<9}", caps.get(5).map_or("", |m| m.as_str()))
+                .parse()
+                .unwrap_or_default();
+
+            // The chapter times cannot be negative, so they are clamped at zero.
+            let total =
+                (((hours * 60 + minutes) * 60 + seconds) * 1_000_000_000 + fraction + shift_ns)
+                    .max(0);
+
+            let secs = total / 1_000_000_000;
+            format!(
+                "<{0}>{1:02}:{2:02}:{3:02}.{4:09}</{0}>",
+                &caps[1],
+                secs / 3600,
+                (secs % 3600) / 60,
+                secs % 60,
+                total % 1_000_000_000
+            )
+        });
+
+        match fs::write(path, shifted.as_ref()) {
+            Ok(_) => true,
+            Err(e) => {
+                logger::log(format!("Failed to write chapters file: {e:?}"), false);
+                false
+            }
+        }
+    }
+
+    /// Detect any subtitle tracks that should be flagged as forced, based on their titles and sizes.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A set containing the indices of the subtitle tracks that should be flagged as forced.
+    fn detect_forced_subtitles(&self, params: &UnifiedParams) -> HashSet<usize> {
+        let mut forced = HashSet::new();
+        let Some(detection) = &params.subtitle_tracks.forced_detection else {
+            return forced;
+        };
+
+        // The sizes of the extracted subtitle tracks. These won't be available during a dry run.
+        let sizes: Vec<Option<u64>> = self
+            .media
+            .tracks
+            .iter()
+            .map(|t| {
+                if t.track_type != TrackType::Subtitle {
+                    return None;
+                }
+
+                let path = utils::join_path_segments(
+                    &self.get_temp_for_output_type("tracks"),
+                    &[t.get_out_file_name()],
+                );
+                fs::metadata(path).ok().map(|m| m.len())
+            })
+            .collect();
+
+        for (i, track) in self.media.tracks.iter().enumerate() {
+            if track.track_type != TrackType::Subtitle {
+                continue;
+            }
+
+            if detection.is_title_match(&track.title) {
+                forced.insert(i);
+                continue;
+            }
+
+            // Compare the size of the track against the largest subtitle track with the same language.
+            let (Some(ratio), Some(size)) = (detection.max_size_ratio, sizes[i]) else {
+                continue;
+            };
+            let largest = self
+                .media
+                .tracks
+                .iter()
+                .zip(&sizes)
+                .filter(|(t, _)| t.language == track.language)
+                .filter_map(|(_, s)| *s)
+                .max()
+                .unwrap_or_default();
+            if (size as f64) < (largest as f64) * ratio {
+                forced.insert(i);
+            }
+        }
+
+        let mut indices: Vec<&usize> = forced.iter().collect();
+        indices.sort();
+        for i in indices {
+            logger::log(
+                format!(
+                    "Subtitle track {} was detected as forced.",
+                    self.media.tracks[*i].id
+                ),
+                false,
+            );
+        }
+
+        forced
+    }
+
     /// Apply any additional track parameters, such as default, forced, etc.
     ///
     /// # Arguments
@@ -403,6 +831,18 @@ impl MediaFile {
             self.muxing_args
                 .push(format!("0:{}", utils::bool_to_yes_no(v)));
         }
+
+        // Set the track name, if specified. An empty name will clear the existing name.
+        if let Some(name) = &track_params.name {
+            self.muxing_args.push("--track-name".to_string());
+            self.muxing_args.push(format!("0:{name}"));
+        }
+
+        // Set the compression method, if specified.
+        if let Some(compression) = &track_params.compression {
+            self.muxing_args.push("--compression".to_string());
+            self.muxing_args.push(format!("0:{compression}"));
+        }
     }
 
     /// Apply the parameters related the tracks to be added to the media file.
@@ -411,6 +851,34 @@ impl MediaFile {
     ///
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
     fn apply_track_mux_params(&mut self, params: &UnifiedParams) {
+        let auto_default = params.misc.auto_default_tracks.unwrap_or(false);
+        let clear_titles = params.misc.clear_track_titles.unwrap_or(false);
+        let mut seen_types = HashSet::new();
+
+        // Find any subtitle tracks that should automatically be flagged as forced.
+        let forced = self.detect_forced_subtitles(params);
+
+        // Find the first track of each type that matches the default language for that type, if specified.
+        let mut language_defaults = HashMap::new();
+        for (track_type, language) in [
+            (TrackType::Audio, &params.audio_tracks.default_for_language),
+            (
+                TrackType::Subtitle,
+                &params.subtitle_tracks.default_for_language,
+            ),
+            (TrackType::Video, &params.video_tracks.default_for_language),
+        ] {
+            let Some(language) = language else {
+                continue;
+            };
+
+            if let Some(index) = self.media.tracks.iter().position(|t| {
+                t.track_type == track_type && t.language.eq_ignore_ascii_case(language)
+            }) {
+                language_defaults.insert(track_type, index);
+            }
+        }
+
         // Iterate over all of the tracks.
         for (i, track) in self.media.tracks.clone().iter().enumerate() {
             let mut delay = track.delay;
@@ -458,9 +926,56 @@ impl MediaFile {
                 self.muxing_args.push(format!("0:{}", track.bit_depth));
             }
 
+            // Set the default flag of the track, unless it was explicitly specified for this track.
+            // A track matching the default language for its type takes precedence, otherwise
+            // the first track of each type may automatically be set as the default.
+            let has_explicit = params
+                .track_params
+                .as_ref()
+                .is_some_and(|tps| tps.iter().any(|t| t.id == i && t.default.is_some()));
+            if !has_explicit {
+                let is_default = if let Some(index) = language_defaults.get(&track.track_type) {
+                    Some(*index == i)
+                } else if auto_default
+                    && matches!(track.track_type, TrackType::Audio | TrackType::Subtitle)
+                {
+                    Some(seen_types.insert(track.track_type.clone()))
+                } else {
+                    None
+                };
+
+                if let Some(is_default) = is_default {
+                    self.muxing_args.push("--default-track-flag".to_string());
+                    self.muxing_args
+                        .push(format!("0:{}", utils::bool_to_yes_no(is_default)));
+                }
+            }
+
+            // Flag the track as forced, if it was detected as such and the forced
+            // flag was not explicitly specified for this track.
+            let has_explicit_forced = params
+                .track_params
+                .as_ref()
+                .is_some_and(|tps| tps.iter().any(|t| t.id == i && t.forced.is_some()));
+            if forced.contains(&i) && !has_explicit_forced {
+                self.muxing_args.push("--forced-display-flag".to_string());
+                self.muxing_args.push("0:yes".to_string());
+            }
+
             // Apply any additional track parameters, if any were specified.
             self.apply_additional_track_mux_params(i, params);
 
+            // Remove any existing track title, if required.
+            // Any explicitly specified track name will take precedence.
+            let has_name = params
+                .track_params
+                .as_ref()
+                .is_some_and(|tps| tps.iter().any(|t| t.id == i && t.name.is_some()));
+            if clear_titles && !has_name {
+                self.muxing_args.push("--track-name".to_string());
+                self.muxing_args.push("0:".to_string());
+            }
+
             // Specify the track language. We set undefined for any video tracks.
             self.muxing_args.push("--language".to_string());
             if track.track_type == TrackType::Video {
@@ -479,121 +994,329 @@ impl MediaFile {
     ///
     /// # Arguments
     ///
+    /// * `title` - The title of the media file.
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
-    fn apply_tag_mux_params(&mut self, params: &UnifiedParams) {
-        let path = params.misc.tags_path.clone().unwrap_or_default();
-        if !utils::file_exists(&path) {
-            return;
-        }
+    fn apply_tag_mux_params(&mut self, title: &str, params: &UnifiedParams) {
+        let path = match &params.misc.tags_path {
+            Some(path) if utils::file_exists(path) => path.clone(),
+            Some(_) => return,
+            None => match self.generate_tags_file(title, params) {
+                Some(path) => path,
+                None => return,
+            },
+        };
 
         // Set the global tags argument.
         self.muxing_args.push("--global-tags".to_string());
         self.muxing_args.push(path);
     }
 
-    /// Convert each audio track found within the media file.
+    /// Generate a tags file containing the title of the media file, within the temporary directory.
     ///
     /// # Arguments
     ///
-    /// * `params` - The conversion parameters to be applied to the tracks.
-    pub fn convert_all_audio(&mut self, params: &AudioConvertParams) -> bool {
-        if params.codec.is_none() {
-            return true;
-        };
-
-        // This is the conversion codec type, converted into the
-        // local codec type. These need to be segregated as they have different purposes.
-        let out_codec = &params.codec.clone().unwrap().into();
-
-        // A list of the updated track indices.
-        let mut update_indices = Vec::new();
-
-        // Iterate through all audio tracks.
-        for (i, t) in self
-            .media
-            .tracks
-            .iter()
-            .enumerate()
-            .filter(|(_, x)| x.track_type == TrackType::Audio)
-        {
-            logger::log_inline(
-                format!("Converting audio track {} to '{out_codec:?}'...", t.id),
-                false,
-            );
-
-            // Determine the output file name.
-            let mut in_file_path = t.get_input_file_path();
-            let out_file_path = t.get_output_file_path(out_codec);
-
-            let mut success = true;
-            if in_file_path == out_file_path {
-                // In the case where the input and output files have the same
-                //   name (by having the same codec type), we need to rename
-                //   the original to avoid attempting to overwrite the original
-                //   while also trying to convert it. Needless to say, that does not work.
-                let out_ext = MediaFileTrack::get_extension_from_codec(out_codec);
-                let new_file_path = in_file_path
-                    .replace(&t.get_out_file_name(), &format!("moved{}.{out_ext}", t.id));
-
-                if fs::rename(&in_file_path, &new_file_path).is_err() {
-                    logger::log(" unable to move input file, unable to encode.", false);
-                    success = false;
-                } else {
-                    in_file_path = new_file_path;
-                }
-            }
-
-            // Was the prior step successful before attempting to encode the track?
-            if success {
-                success = converters::convert_audio_file(t, &in_file_path, &out_file_path, params);
-            }
-
-            // Was the conversion successful? If so, add the index to the list
-            // so that the codec can be updated later.
-            if success {
-                update_indices.push(i);
-                logger::log(" success!", false);
-            } else {
-                logger::log(" failed!", false);
-            }
+    /// * `title` - The title of the media file.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// The path to the generated tags file, if it was successfully written.
+    fn generate_tags_file(&self, title: &str, params: &UnifiedParams) -> Option<String> {
+        let path = utils::join_path_segments(&self.get_temp_path(), &["tags.xml"]);
+        if params.misc.dry_run {
+            return Some(path);
+        }
+
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n\
+             <!DOCTYPE Tags SYSTEM \"matroskatags.dtd\">\r\n\
+             <Tags>\r\n\
+             \x20 <Tag>\r\n\
+             \x20   <Targets>\r\n\
+             \x20     <TargetTypeValue>50</TargetTypeValue>\r\n\
+             \x20   </Targets>\r\n\
+             \x20   <Simple>\r\n\
+             \x20     <Name>TITLE</Name>\r\n\
+             \x20     <String>{}</String>\r\n\
+             \x20   </Simple>\r\n\
+             \x20 </Tag>\r\n\
+             </Tags>\r\n",
+            utils::escape_xml(title)
+        );
 
-            // Output the FFmpeg parameters, if the debug flag is set.
-            if DEBUG_PARAMS {
-                let args = params
-                    .as_ffmpeg_argument_list(t, &in_file_path, &out_file_path)
-                    .unwrap();
+        match fs::write(&path, xml) {
+            Ok(_) => Some(path),
+            Err(e) => {
                 logger::log(
-                    format!(
-                        "[INFO] ffmpeg command line: \"{}\" {}",
-                        paths::PATHS.ffmpeg,
-                        &args.join(" ")
-                    ),
+                    format!("An error occurred while attempting to write the tags file: {e:?}"),
                     false,
                 );
+                None
             }
-
-            if !success {
-                return false;
-            }
-        }
-
-        // Update the codecs of the converted tracks.
-        for index in update_indices {
-            self.media.tracks[index].codec = out_codec.clone();
         }
-
-        true
     }
 
-    /// Convert each video track found within the media file.
+    /// Convert a single audio track.
     ///
     /// # Arguments
     ///
-    /// * `params` - The conversion parameters to be applied to the subtitle tracks.
-    #[allow(unused)]
-    pub fn convert_all_subtitles(&mut self, params: &SubtitleConvertParams) {
-        if params.codec.is_none() {
-            return;
+    /// * `track` - The audio track to be converted.
+    /// * `out_codec` - The codec into which the track should be converted.
+    /// * `params` - The conversion parameters to be applied to the track.
+    /// * `misc` - The [`MiscParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if the track was successfully converted, false otherwise.
+    fn convert_audio_track(
+        track: &MediaFileTrack,
+        out_codec: &Codec,
+        params: &AudioConvertParams,
+        misc: &MiscParams,
+    ) -> bool {
+        logger::log_inline(
+            format!("Converting audio track {} to '{out_codec:?}'...", track.id),
+            false,
+        );
+
+        // Determine the output file name.
+        let mut in_file_path = track.get_input_file_path();
+        let out_file_path = track.get_output_file_path(out_codec);
+
+        let mut success = true;
+        if in_file_path == out_file_path {
+            // In the case where the input and output files have the same
+            //   name (by having the same codec type), we need to rename
+            //   the original to avoid attempting to overwrite the original
+            //   while also trying to convert it. Needless to say, that does not work.
+            // The new name includes the track ID, so it is unique to this track
+            //   even when several tracks are being converted concurrently.
+            let out_ext = MediaFileTrack::get_extension_from_codec(out_codec);
+            let new_file_path = in_file_path.replace(
+                &track.get_out_file_name(),
+                &format!("moved{}.{out_ext}", track.id),
+            );
+
+            if !misc.dry_run && fs::rename(&in_file_path, &new_file_path).is_err() {
+                logger::log(" unable to move input file, unable to encode.", false);
+                success = false;
+            } else {
+                in_file_path = new_file_path;
+            }
+        }
+
+        // Was the prior step successful before attempting to encode the track?
+        if success {
+            success =
+                converters::convert_audio_file(track, &in_file_path, &out_file_path, params, misc);
+        }
+
+        if success {
+            logger::log(" success!", false);
+        } else {
+            logger::log(" failed!", false);
+        }
+
+        // Output the FFmpeg parameters, if the debug flag is set.
+        if misc.debug_commands.unwrap_or(false) {
+            let args = params
+                .as_ffmpeg_argument_list(track, &in_file_path, &out_file_path)
+                .unwrap();
+            logger::log_level(
+                Level::Info,
+                format!(
+                    "ffmpeg command line: \"{}\" {}",
+                    paths::PATHS.ffmpeg,
+                    &args.join(" ")
+                ),
+            );
+        }
+
+        success
+    }
+
+    /// Convert each audio track found within the media file.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] if any of the tracks could not be converted.
+    pub fn convert_all_audio(&mut self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        // A list of the indices of the audio tracks to be converted, along with the codec
+        // and the conversion parameters to be used for each track.
+        // The output codec is converted into the local codec type.
+        // These need to be segregated as they have different purposes.
+        let jobs: Vec<(usize, Codec, &AudioConvertParams)> = self
+            .media
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.track_type == TrackType::Audio)
+            .filter_map(|(i, track)| {
+                let conversion = MediaFile::get_audio_conversion_params(i, params)?;
+
+                // Tracks with a codec in the skip list will be copied through as-is.
+                if conversion
+                    .skip_codecs
+                    .as_ref()
+                    .is_some_and(|codecs| codecs.contains(&track.codec))
+                {
+                    logger::log(
+                        format!(
+                            "Audio track {} has codec '{:?}' and will not be converted.",
+                            track.id, track.codec
+                        ),
+                        false,
+                    );
+                    return None;
+                }
+
+                // Tracks that already match the conversion parameters will be copied through as-is.
+                if !conversion.force_reencode.unwrap_or(false) && conversion.matches_source(track) {
+                    logger::log(
+                        format!(
+                            "Audio track {} already matches the conversion parameters and will not be converted.",
+                            track.id
+                        ),
+                        false,
+                    );
+                    return None;
+                }
+
+                let out_codec = conversion.codec.clone()?.into();
+                Some((i, out_codec, conversion))
+            })
+            .collect();
+
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        // Determine how many tracks may be converted at once.
+        let workers = match &params.audio_tracks.conversion {
+            Some(ac) if ac.parallel_tracks.unwrap_or(false) => {
+                ac.max_parallel_tracks.unwrap_or(jobs.len())
+            }
+            _ => 1,
+        };
+
+        let success = if workers > 1 {
+            self.convert_audio_tracks_parallel(&jobs, workers, &params.misc)
+        } else {
+            self.convert_audio_tracks_sequential(&jobs, &params.misc)
+        };
+
+        if !success {
+            return Err(ProcessError::ConversionFailed);
+        }
+
+        // Update the codecs of the converted tracks.
+        for (index, out_codec, _) in jobs {
+            self.media.tracks[index].codec = out_codec;
+            self.media.tracks[index].converted = true;
+        }
+
+        Ok(())
+    }
+
+    /// Convert the specified audio tracks concurrently, using a bounded number of scoped threads.
+    ///
+    /// Scoped threads are used in place of a thread pool, as waiting on a pool from a worker of the
+    /// per-file pool would allow that worker to start processing another file, mixing up the buffered logs.
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs` - The indices of the tracks to be converted, along with their output codecs and conversion parameters.
+    /// * `workers` - The maximum number of tracks to be converted at once.
+    /// * `misc` - The [`MiscParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if all of the tracks were successfully converted, false otherwise.
+    fn convert_audio_tracks_parallel(
+        &self,
+        jobs: &[(usize, Codec, &AudioConvertParams)],
+        workers: usize,
+        misc: &MiscParams,
+    ) -> bool {
+        let tracks = &self.media.tracks;
+        let next = AtomicUsize::new(0);
+
+        let mut results = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.clamp(1, jobs.len().max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut completed = Vec::new();
+                        loop {
+                            let job = next.fetch_add(1, Ordering::SeqCst);
+                            let Some((i, out_codec, params)) = jobs.get(job) else {
+                                break;
+                            };
+
+                            // Buffer the output so that it can be written in track order.
+                            logger::begin_buffering();
+                            let success = MediaFile::convert_audio_track(
+                                &tracks[*i],
+                                out_codec,
+                                params,
+                                misc,
+                            );
+                            completed.push((job, success, logger::take_buffered()));
+                        }
+                        completed
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap_or_default())
+                .collect::<Vec<_>>()
+        });
+        results.sort_unstable_by_key(|(job, _, _)| *job);
+
+        // Any job lost to a panicked thread is treated as a failure.
+        let mut success = results.len() == jobs.len();
+        for (_, s, entries) in results {
+            logger::replay(entries);
+            success &= s;
+        }
+
+        success
+    }
+
+    /// Convert the specified audio tracks sequentially, stopping at the first failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs` - The indices of the tracks to be converted, along with their output codecs and conversion parameters.
+    /// * `misc` - The [`MiscParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if all of the tracks were successfully converted, false otherwise.
+    fn convert_audio_tracks_sequential(
+        &self,
+        jobs: &[(usize, Codec, &AudioConvertParams)],
+        misc: &MiscParams,
+    ) -> bool {
+        jobs.iter().all(|(i, out_codec, params)| {
+            MediaFile::convert_audio_track(&self.media.tracks[*i], out_codec, params, misc)
+        })
+    }
+
+    /// Convert each video track found within the media file.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The conversion parameters to be applied to the subtitle tracks.
+    #[allow(unused)]
+    pub fn convert_all_subtitles(&mut self, params: &SubtitleConvertParams) {
+        if params.codec.is_none() {
+            return;
         };
 
         todo!("not yet implemented");
@@ -621,7 +1344,7 @@ impl MediaFile {
     pub(crate) fn dump_json(json: &str) {
         use std::{fs::File, io::Write};
 
-        let fp = utils::join_path_segments(&paths::PATHS.temp, &["output.json"]);
+        let fp = utils::join_path_segments(&paths::temp_dir(), &["output.json"]);
         let mut file = File::create(fp).expect("create failed");
         Write::write_all(&mut file, json.as_bytes()).expect("write failed");
     }
@@ -631,21 +1354,156 @@ impl MediaFile {
     /// # Arguments
     ///
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
-    fn extract(&mut self, params: &UnifiedParams) -> bool {
-        if !self.extract_tracks() {
-            return false;
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] if the extraction failed.
+    fn extract(&mut self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        // Where several types of item are to be extracted, attempt to extract them with a single
+        // process so that the source file only needs to be read once.
+        let actions: Vec<(&str, Vec<String>)> = [
+            ("tracks", self.get_track_extract_args()),
+            ("attachments", self.get_attachment_extract_args(params)),
+            ("chapters", MediaFile::get_chapter_extract_args(params)),
+        ]
+        .into_iter()
+        .filter(|(_, args)| !args.is_empty())
+        .collect();
+
+        if actions.len() > 1 {
+            if self.extract_combined(&actions, params) {
+                return Ok(());
+            }
+
+            logger::log(
+                "The combined extraction failed, the items will be extracted separately.",
+                false,
+            );
+
+            // Any partially written files from the failed extraction must not be muxed.
+            let output_types: Vec<&str> = actions.iter().map(|(t, _)| *t).collect();
+            if !self.clear_temp_directories(&output_types) {
+                logger::log_level(
+                    Level::Error,
+                    "Failed to clear the output of the combined extraction.",
+                );
+                return Err(ProcessError::ExtractionFailed);
+            }
         }
 
-        if !self.extract_attachments(params) {
-            self.attachments.clear();
-            return false;
+        if self.extract_separately(params) {
+            Ok(())
+        } else {
+            Err(ProcessError::ExtractionFailed)
         }
+    }
 
-        if !self.extract_chapters(params) {
-            return false;
+    /// Extract several types of item from a MKV file, using a single process.
+    ///
+    /// # Arguments
+    ///
+    /// * `actions` - A list of the types of item to be extracted, along with the arguments for each type.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if the extraction was successful, false otherwise.
+    fn extract_combined(&self, actions: &[(&str, Vec<String>)], params: &UnifiedParams) -> bool {
+        let names: Vec<&str> = actions.iter().map(|(arg_type, _)| *arg_type).collect();
+        logger::log_inline(format!("Extracting {}...", names.join(", ")), false);
+
+        let (code, warnings) = mkvtoolnix::run_extract_multiple(
+            &self.file_path,
+            &self.get_temp_path(),
+            actions,
+            &params.misc,
+        );
+
+        let r = match code {
+            0 | 1 => {
+                logger::log(" success!", false);
+                true
+            }
+            2 => {
+                logger::log(" failed!", false);
+                false
+            }
+            _ => true,
+        };
+        self.add_warnings(warnings);
+
+        // Output the mkvextract parameters, if the debug flag is set.
+        if params.misc.debug_commands.unwrap_or(false) {
+            let args: Vec<String> = actions
+                .iter()
+                .map(|(arg_type, args)| format!("{arg_type} {}", args.join(" ")))
+                .collect();
+            logger::log_level(
+                Level::Info,
+                format!(
+                    "mkvextract command line: \"{}\" {}",
+                    mkvtoolnix::get_exe("mkvextract"),
+                    args.join(" ")
+                ),
+            );
         }
 
-        true
+        r
+    }
+
+    /// Extract the tracks, attachments and chapters from a MKV file, using a separate process for each.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if the extraction was successful, false otherwise.
+    fn extract_separately(&mut self, params: &UnifiedParams) -> bool {
+        // The tracks, attachments and chapters are extracted into separate temporary
+        // directories, so the extractions can safely be run concurrently.
+        // The output of each extraction is buffered so that it can be written in order.
+        // Once an extraction has failed, any extraction that hasn't yet started mkvextract will be
+        // skipped, as the file will be abandoned regardless of whether the others succeed.
+        *self.extraction_aborted.get_mut() = false;
+        let this = &*self;
+        let results: Vec<(bool, Vec<(String, bool)>)> = thread::scope(|scope| {
+            let extractions: [fn(&MediaFile, &UnifiedParams) -> bool; 3] = [
+                MediaFile::extract_tracks,
+                MediaFile::extract_attachments,
+                MediaFile::extract_chapters,
+            ];
+
+            let handles: Vec<_> = extractions
+                .into_iter()
+                .map(|extract| {
+                    scope.spawn(move || {
+                        logger::begin_buffering();
+                        let success = extract(this, params);
+                        (success, logger::take_buffered())
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or((false, Vec::new())))
+                .collect()
+        });
+
+        let mut success = [true; 3];
+        for (i, (s, entries)) in results.into_iter().enumerate() {
+            logger::replay(entries);
+            success[i] = s;
+        }
+
+        let [tracks, attachments, chapters] = success;
+        if !attachments {
+            self.attachments.clear();
+        }
+
+        tracks && attachments && chapters
     }
 
     /// Extract the attachments from a MKV file, if present.
@@ -668,21 +1526,15 @@ impl MediaFile {
 
         logger::log_inline("Extracting attachments...", false);
 
-        // Note: attachments indices do not start at index 0,
-        // so we have to add one to each of the IDs.
-        let args: Vec<String> = self
-            .attachments
-            .iter()
-            .enumerate()
-            .map(|(i, a)| format!("{}:{a}", i + 1))
-            .collect();
+        let args = self.get_attachment_extract_args(params);
 
-        let r = match mkvtoolnix::run_extract(
-            &self.file_path,
-            &self.get_temp_path(),
-            "attachments",
-            &args,
-        ) {
+        let Some((code, warnings)) = self.run_extract_unless_aborted("attachments", &args, params)
+        else {
+            logger::log(" skipped, as another extraction failed.", false);
+            return false;
+        };
+
+        let r = match code {
             0 | 1 => {
                 logger::log(" extraction complete.", false);
                 true
@@ -693,16 +1545,17 @@ impl MediaFile {
             }
             _ => true,
         };
+        self.add_warnings(warnings);
 
         // Output the mkvextract parameters, if the debug flag is set.
-        if DEBUG_PARAMS {
-            logger::log(
+        if params.misc.debug_commands.unwrap_or(false) {
+            logger::log_level(
+                Level::Info,
                 format!(
-                    "[INFO] mkvextract command line: \"{}\" {}",
+                    "mkvextract command line: \"{}\" {}",
                     mkvtoolnix::get_exe("mkvextract"),
                     &args.join(" ")
                 ),
-                false,
             );
         }
 
@@ -721,12 +1574,16 @@ impl MediaFile {
 
         logger::log_inline("Extracting chapters...", false);
 
-        let r = match mkvtoolnix::run_extract(
-            &self.file_path,
-            &self.get_temp_path(),
+        let Some((code, warnings)) = self.run_extract_unless_aborted(
             "chapters",
-            &["chapters.xml".to_string()],
-        ) {
+            &MediaFile::get_chapter_extract_args(params),
+            params,
+        ) else {
+            logger::log(" skipped, as another extraction failed.", false);
+            return false;
+        };
+
+        let r = match code {
             0 | 1 => {
                 logger::log(" success!", false);
                 true
@@ -737,15 +1594,16 @@ impl MediaFile {
             }
             _ => true,
         };
+        self.add_warnings(warnings);
 
         // Output the mkvextract parameters, if the debug flag is set.
-        if DEBUG_PARAMS {
-            logger::log(
+        if params.misc.debug_commands.unwrap_or(false) {
+            logger::log_level(
+                Level::Info,
                 format!(
-                    "[INFO] mkvextract command line: \"{}\" chapters.xml",
+                    "mkvextract command line: \"{}\" chapters.xml",
                     mkvtoolnix::get_exe("mkvextract")
                 ),
-                false,
             );
         }
 
@@ -753,7 +1611,11 @@ impl MediaFile {
     }
 
     /// Extract the tracks from a MKV file.
-    pub fn extract_tracks(&self) -> bool {
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    pub fn extract_tracks(&self, params: &UnifiedParams) -> bool {
         let tracks = &self.media.tracks;
         if tracks.is_empty() {
             logger::log("No tracks to extract.", false);
@@ -762,18 +1624,15 @@ impl MediaFile {
 
         logger::log_inline("Extracting tracks...", false);
 
-        // Note: track indices start at index 0.
-        let args: Vec<String> = tracks
-            .iter()
-            .map(|track| format!("{}:{}", track.id, track.get_out_file_name()))
-            .collect();
+        let args = self.get_track_extract_args();
 
-        let r = match mkvtoolnix::run_extract(
-            &self.file_path,
-            &self.get_temp_path(),
-            "tracks",
-            &args,
-        ) {
+        let Some((code, warnings)) = self.run_extract_unless_aborted("tracks", &args, params)
+        else {
+            logger::log(" skipped, as another extraction failed.", false);
+            return false;
+        };
+
+        let r = match code {
             0 | 1 => {
                 logger::log(" success!", false);
                 true
@@ -784,22 +1643,112 @@ impl MediaFile {
             }
             _ => true,
         };
+        self.add_warnings(warnings);
 
         // Output the mkvextract parameters, if the debug flag is set.
-        if DEBUG_PARAMS {
-            logger::log(
+        if params.misc.debug_commands.unwrap_or(false) {
+            logger::log_level(
+                Level::Info,
                 format!(
-                    "[INFO] mkvextract command line: \"{}\" {}",
+                    "mkvextract command line: \"{}\" {}",
                     mkvtoolnix::get_exe("mkvextract"),
                     &args.join(" ")
                 ),
-                false,
             );
         }
 
         r
     }
 
+    /// Run mkvextract for a single type of item, unless one of the concurrent extractions has already failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_type` - The type of item to be extracted.
+    /// * `args` - The arguments for the extraction.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the exit code of mkvextract and any warnings that it issued, or None if the extraction was skipped.
+    fn run_extract_unless_aborted(
+        &self,
+        arg_type: &str,
+        args: &[String],
+        params: &UnifiedParams,
+    ) -> Option<(i32, Vec<String>)> {
+        if self.extraction_aborted.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let (code, warnings) = mkvtoolnix::run_extract(
+            &self.file_path,
+            &self.get_temp_path(),
+            arg_type,
+            args,
+            &params.misc,
+        );
+        if code == 2 {
+            self.extraction_aborted.store(true, Ordering::SeqCst);
+        }
+
+        Some((code, warnings))
+    }
+
+    /// Get the mkvextract arguments needed to extract the attachments from a MKV file.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A list of the arguments, which will be empty if there are no attachments to be extracted.
+    fn get_attachment_extract_args(&self, params: &UnifiedParams) -> Vec<String> {
+        if !params.attachments.import_from_original {
+            return Vec::new();
+        }
+
+        // Note: attachments indices do not start at index 0,
+        // so we have to add one to each of the IDs.
+        self.attachments
+            .iter()
+            .enumerate()
+            .map(|(i, a)| format!("{}:{a}", i + 1))
+            .collect()
+    }
+
+    /// Get the mkvextract arguments needed to extract the chapters from a MKV file.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A list of the arguments, which will be empty if the chapters are not to be extracted.
+    fn get_chapter_extract_args(params: &UnifiedParams) -> Vec<String> {
+        if params.chapters.import_from_original {
+            vec!["chapters.xml".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Get the mkvextract arguments needed to extract the tracks from a MKV file.
+    ///
+    /// # Returns
+    ///
+    /// A list of the arguments, which will be empty if there are no tracks to be extracted.
+    fn get_track_extract_args(&self) -> Vec<String> {
+        // Note: track indices start at index 0.
+        self.media
+            .tracks
+            .iter()
+            .map(|track| format!("{}:{}", track.id, track.get_out_file_name()))
+            .collect()
+    }
+
     /// Filter files from a [`DirEntry`] iterator filter_map.
     ///
     /// # Arguments
@@ -879,17 +1828,38 @@ impl MediaFile {
     ///
     /// # Returns
     ///
-    /// A boolean, true if the filtering met the track targets (if applicable), false otherwise.
-    pub fn filter_tracks(&mut self, params: &UnifiedParams) -> bool {
+    /// A result, which will contain the [`ProcessError`] if the filtering did not meet the track targets (if applicable).
+    pub fn filter_tracks(&mut self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        // Check that enough matching tracks exist before doing any further work.
+        self.validate_available_tracks(params)?;
+
         // Create a new vector to hold the tracks that we want to keep.
         let mut kept = Vec::with_capacity(self.media.tracks.len());
 
+        let dedupe = params.misc.dedupe_tracks.unwrap_or(false);
+
         for (i, track) in &mut self.media.tracks.iter().enumerate() {
             // If we don't need to keep this track, then skip to the next track.
             if !self.should_keep_track(&track.track_type, i, params) {
                 continue;
             }
 
+            // Skip any track that appears to duplicate a track that has already been kept.
+            if dedupe
+                && kept
+                    .iter()
+                    .any(|k: &MediaFileTrack| k.is_duplicate_of(track))
+            {
+                logger::log(
+                    format!(
+                        "The {} track {} duplicates a previous track and will be skipped.",
+                        track.track_type, track.id
+                    ),
+                    false,
+                );
+                continue;
+            }
+
             // Add the track to the kept list.
             kept.push(track.clone());
 
@@ -900,9 +1870,7 @@ impl MediaFile {
                 .or_default() += 1;
         }
 
-        if !self.validate_filter_targets(params) {
-            return false;
-        }
+        self.validate_filter_targets(params)?;
 
         logger::log(
             format!("{} tracks kept after filtering.", kept.len()),
@@ -912,7 +1880,7 @@ impl MediaFile {
         // Assign the kept tracks back into the container object.
         self.media.tracks = kept;
 
-        true
+        Ok(())
     }
 
     /// Create a [`MediaFile] instance from a media file path.
@@ -920,15 +1888,21 @@ impl MediaFile {
     /// # Arguments
     ///
     /// * `fp` - The path to the media file.
-    pub fn from_path(fp: &str) -> Option<Self> {
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file, or `None` if the
+    ///   file is only being inspected, in which case no files will be created.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the [`MediaFile`] instance, or the reason that it could not be created.
+    pub fn from_path(fp: &str, params: Option<&UnifiedParams>) -> Result<Self, String> {
         if !utils::file_exists(fp) {
-            return None;
+            return Err("the file does not exist".to_string());
         }
 
-        logger::subsection(
-            format!("File {}", UNIQUE_ID.fetch_add(0, Ordering::SeqCst) + 1),
-            false,
-        );
+        // The ID is assigned once, so that the logged file number matches the temporary directory.
+        let id = UNIQUE_ID.fetch_add(1, Ordering::SeqCst);
+
+        logger::subsection(format!("File {id}"), false);
         logger::log_inline(
             format!("Extracting MediaInfo JSON data for file '{fp}'..."),
             false,
@@ -945,44 +1919,129 @@ impl MediaFile {
             Ok(o) => String::from_utf8_lossy(&o.stdout).to_string(),
             Err(e) => {
                 logger::log(format!(" Error: {e}"), false);
-                return None;
+                return Err(format!("MediaInfo could not be run: {e}"));
             }
         };
 
         logger::log(" Done.", false);
 
-        // Were we able to successfully parse the output?
-        if let Some(mut mf) = MediaFile::parse_json(&json) {
-            mf.id = UNIQUE_ID.fetch_add(1, Ordering::SeqCst);
+        // Attempt to parse the output. The file can't be processed if this fails.
+        let mut mf = MediaFile::parse_json(&json, params)?;
+        mf.id = id;
 
-            // Set the media file path variable.
-            mf.file_path = fp.to_string();
+        // Set the media file path variable.
+        mf.file_path = fp.to_string();
 
-            // Do we have any attachments? If so, copy them to the main struct.
-            mf.attachments
-                .clone_from(&mf.media.tracks[0].extra_info.attachments);
+        // Do we have any attachments? If so, copy them to the main struct.
+        // The attachments are held by the general track, which may not be the first track
+        // or may be absent entirely.
+        mf.attachments = mf
+            .media
+            .tracks
+            .iter()
+            .find(|t| t.track_type == TrackType::General)
+            .map(|t| t.extra_info.attachments.clone())
+            .unwrap_or_default();
 
-            // Set up the temporary directory structure for the file.
+        // Set up the temporary directory structure for the file.
+        // No files should be created during a dry run.
+        if params.is_some_and(|p| !p.misc.dry_run) {
             mf.init_temp_directory();
+        }
+
+        logger::log(format!("Total tracks: {}", mf.media.tracks.len()), false);
+        logger::log(
+            format!("Total attachments: {}", mf.attachments.len()),
+            false,
+        );
+
+        mf.muxing_args = Vec::with_capacity(100);
+
+        // Return the MediaFile object.
+        Ok(mf)
+    }
+
+    /// Log a summary of the tracks and attachments within the media file.
+    pub fn log_info(&self) {
+        logger::section(format!("Media File: {}", self.file_path), true);
+
+        logger::log(
+            format!(
+                "{:<6}{:<10}{:<18}{:<10}{:<10}{:<12}{}",
+                "ID", "Type", "Codec", "Language", "Channels", "Resolution", "Delay"
+            ),
+            true,
+        );
+
+        for track in &self.media.tracks {
+            // The general track holds information about the file, rather than a track.
+            if track.track_type == TrackType::General {
+                continue;
+            }
+
+            let channels = if track.track_type == TrackType::Audio && track.channels > 0 {
+                track.channels.to_string()
+            } else {
+                "-".to_string()
+            };
+            let resolution = if track.track_type == TrackType::Video {
+                format!("{}x{}", track.width, track.height)
+            } else {
+                "-".to_string()
+            };
 
-            logger::log(format!("Total tracks: {}", mf.media.tracks.len()), false);
             logger::log(
-                format!("Total attachments: {}", mf.attachments.len()),
-                false,
+                format!(
+                    "{:<6}{:<10}{:<18}{:<10}{:<10}{:<12}{} ms",
+                    track.id,
+                    track.track_type.to_string(),
+                    format!("{:?}", track.codec),
+                    track.language,
+                    channels,
+                    resolution,
+                    track.delay
+                ),
+                true,
             );
+        }
 
-            mf.muxing_args = Vec::with_capacity(100);
-
-            // Return the MediaFile object.
-            Some(mf)
+        logger::log("", true);
+        if self.attachments.is_empty() {
+            logger::log("No attachments.", true);
         } else {
-            None
+            logger::log(format!("Attachments ({}):", self.attachments.len()), true);
+            for name in &self.attachments {
+                logger::log(format!("\t{name}"), true);
+            }
         }
     }
 
+    /// Get the audio conversion parameters that should be applied to a given track.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The ID of the track.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// The track-specific conversion parameters, if specified, or the global audio conversion parameters otherwise.
+    fn get_audio_conversion_params(
+        track_id: usize,
+        params: &UnifiedParams,
+    ) -> Option<&AudioConvertParams> {
+        params
+            .track_params
+            .iter()
+            .flatten()
+            .find(|t| t.id == track_id)
+            .and_then(|t| t.audio_conversion.as_ref())
+            .or(params.audio_tracks.conversion.as_ref())
+    }
+
     /// Get the path to the temporary folder for this media file.
     fn get_temp_path(&self) -> String {
-        utils::join_path_segments(&paths::PATHS.temp, &[self.id.to_string()])
+        utils::join_path_segments(&paths::temp_dir(), &[self.id.to_string()])
     }
 
     /// Get the path to the temporary folder for the given output type for this media file.
@@ -1006,6 +2065,28 @@ impl MediaFile {
         success
     }
 
+    /// Remove the contents of the temporary subdirectories for the given output types.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_types` - The names of the subdirectories to be cleared.
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if the subdirectories were successfully cleared, false otherwise.
+    fn clear_temp_directories(&self, output_types: &[&str]) -> bool {
+        let mut success = true;
+        for dir in output_types {
+            let p = self.get_temp_for_output_type(dir);
+            if Path::new(&p).exists() {
+                success &= fs::remove_dir_all(&p).is_ok();
+            }
+            success &= fs::create_dir_all(&p).is_ok();
+        }
+
+        success
+    }
+
     /// Delete a file or folder, if the argument specifies it, by the method specified [`DeletionOptions`] method.
     ///
     /// # Arguments
@@ -1053,18 +2134,21 @@ impl MediaFile {
     /// # Arguments
     ///
     /// * `json` - The JSON string to be parsed.
-    fn parse_json(json: &str) -> Option<MediaFile> {
-        if EXPORT_JSON {
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file, if any.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the parsed [`MediaFile`] instance, or the reason that it could not be parsed.
+    fn parse_json(json: &str, params: Option<&UnifiedParams>) -> Result<MediaFile, String> {
+        if params.is_some_and(|p| p.misc.export_mediainfo_json.unwrap_or(false) && !p.misc.dry_run)
+        {
             MediaFile::dump_json(json);
         }
 
-        match serde_json::from_str::<MediaFile>(json) {
-            Ok(mi) => Some(mi),
-            Err(e) => {
-                logger::log(format!("Error attempting to parse JSON data: {e:?}"), true);
-                None
-            }
-        }
+        serde_json::from_str::<MediaFile>(json).map_err(|e| {
+            logger::log(format!("Error attempting to parse JSON data: {e:?}"), true);
+            format!("the MediaInfo JSON data could not be parsed: {e}")
+        })
     }
 
     /// Process a media file, applying any conversions and filters before remuxing the file.
@@ -1074,14 +2158,31 @@ impl MediaFile {
     /// * `out_path` - The path of the output media file.
     /// * `title` - The title of the media file.
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
-    pub fn process(&mut self, out_path: &str, title: &str, params: &UnifiedParams) -> bool {
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] if the media file could not be processed.
+    pub fn process(
+        &mut self,
+        out_path: &str,
+        title: &str,
+        params: &UnifiedParams,
+    ) -> Result<(), ProcessError> {
         self.output_path = out_path.to_string();
+        self.title = title.to_string();
 
         // Set the file IDs of all child tracks.
         for track in &mut self.media.tracks {
             track.file_id = self.id;
         }
 
+        // Run any pre-extraction processes, if any were requested.
+        if !self.run_commands(RunCommandType::PreExtract, params) {
+            return Err(ProcessError::CommandFailed(RunCommandType::PreExtract));
+        }
+
+        logger::log("", false);
+
         // Filter the attachments based on the filter parameters.
         self.filter_internal_attachments(params);
 
@@ -1090,28 +2191,28 @@ impl MediaFile {
 
         // Filter the tracks based on the filter parameters.
         // If the filtering is unsuccessful then we can't continue.
-        if !self.filter_tracks(params) {
-            return false;
+        self.filter_tracks(params)?;
+
+        // Check that the extracted files can be held within the temporary directory.
+        // No files will be created during a dry run.
+        if !params.misc.dry_run && !self.has_sufficient_temp_space() {
+            return Err(ProcessError::InsufficientTempSpace);
         }
 
         // Extract the files.
-        if !self.extract(params) {
-            return false;
-        }
+        self.extract(params)?;
 
         logger::log("", false);
 
         // Run any pre-conversion processes, if any were requested.
-        self.run_commands(RunCommandType::PreConvert, params);
+        if !self.run_commands(RunCommandType::PreConvert, params) {
+            return Err(ProcessError::CommandFailed(RunCommandType::PreConvert));
+        }
 
         logger::log("", false);
 
         // Convert the audio tracks.
-        if let Some(ac) = &params.audio_tracks.conversion {
-            if ac.codec.is_some() && !self.convert_all_audio(ac) {
-                return false;
-            }
-        }
+        self.convert_all_audio(params)?;
 
         // Convert the subtitle tracks.
         if let Some(_sc) = &params.subtitle_tracks.conversion {
@@ -1126,29 +2227,44 @@ impl MediaFile {
         logger::log("", false);
 
         // Run any post-conversion processes, if any were requested.
-        self.run_commands(RunCommandType::PostConvert, params);
+        if !self.run_commands(RunCommandType::PostConvert, params) {
+            return Err(ProcessError::CommandFailed(RunCommandType::PostConvert));
+        }
 
         logger::log("", false);
 
         // Run any pre-muxing processes, if any were requested.
-        self.run_commands(RunCommandType::PreMux, params);
+        if !self.run_commands(RunCommandType::PreMux, params) {
+            return Err(ProcessError::CommandFailed(RunCommandType::PreMux));
+        }
 
         logger::log("", false);
 
+        // Check that the tracks can be stored within the output container.
+        self.validate_container_codecs(params)?;
+
+        // Apply any substitutions that depend on the tracks that were kept.
+        self.apply_metadata_substitutions(params);
+
         // Remux the media file.
-        if self.remux_file(out_path, title, params) {
-            logger::log("", false);
+        let title = self.title.clone();
+        self.remux_file(out_path, &title, params)?;
 
-            // Run any post-muxing processes, if any were requested.
-            self.run_commands(RunCommandType::PostMux, params);
+        logger::log("", false);
+
+        // Run any post-muxing processes, if any were requested.
+        if !self.run_commands(RunCommandType::PostMux, params) {
+            return Err(ProcessError::CommandFailed(RunCommandType::PostMux));
         }
 
         logger::log("", false);
 
         // Delete the temporary files, if needed.
-        MediaFile::delete_path(&self.get_temp_path(), &params.misc.remove_temp_files);
+        if !params.misc.dry_run {
+            MediaFile::delete_path(&self.get_temp_path(), &params.misc.remove_temp_files);
+        }
 
-        true
+        Ok(())
     }
 
     /// Run any pre-muxing commands.
@@ -1157,23 +2273,30 @@ impl MediaFile {
     ///
     /// * `run_type` - The type of command to be run.
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
-    pub fn run_commands(&self, run_type: RunCommandType, params: &UnifiedParams) {
+    ///
+    /// # Returns
+    ///
+    /// A boolean, true if every command was successfully executed or if failures are to be ignored, false otherwise.
+    pub fn run_commands(&self, run_type: RunCommandType, params: &UnifiedParams) -> bool {
         logger::log_inline("Checking for run commands... ", false);
 
         let run = match params.misc.run.clone() {
             Some(r) => r,
             None => {
                 logger::log("no commands were specified.", false);
-                return;
+                return true;
             }
         };
 
+        let continue_on_error = params.misc.continue_on_run_error.unwrap_or(false);
+
         let commands: Vec<&ProcessRun> = run
             .iter()
             .filter(|f| match run_type {
+                RunCommandType::PreExtract => matches!(f, ProcessRun::PreExtract(_)),
                 RunCommandType::PreConvert => matches!(f, ProcessRun::PreConvert(_)),
                 RunCommandType::PreMux => matches!(f, ProcessRun::PreMux(_)),
-                RunCommandType::PostConvert => matches!(f, ProcessRun::PreConvert(_)),
+                RunCommandType::PostConvert => matches!(f, ProcessRun::PostConvert(_)),
                 RunCommandType::PostMux => matches!(f, ProcessRun::PostMux(_)),
             })
             .collect();
@@ -1182,7 +2305,7 @@ impl MediaFile {
                 format!("no commands of type '{run_type:?}' were specified."),
                 false,
             );
-            return;
+            return true;
         }
 
         logger::log("\n", false);
@@ -1192,12 +2315,7 @@ impl MediaFile {
                 false,
             );
 
-            let command_args = match command {
-                ProcessRun::PreConvert(args)
-                | ProcessRun::PreMux(args)
-                | ProcessRun::PostConvert(args)
-                | ProcessRun::PostMux(args) => args,
-            };
+            let command_args = command.args();
 
             // The path to the command must always be the first in the list.
             // Everything that follows will be assumed to be arguments
@@ -1208,9 +2326,12 @@ impl MediaFile {
                     format!(
                         "Run command of type {run_type:?} was specified, but the path doesn't exist!",
                     ),
-                    false,
+                    true,
                 );
-                return;
+                if continue_on_error {
+                    continue;
+                }
+                return false;
             }
 
             // Go through the arguments list and replace any special tags.
@@ -1224,27 +2345,118 @@ impl MediaFile {
             }
 
             for arg in &mut args {
+                *arg = arg.replace("%index%", self.index.as_deref().unwrap_or_default());
+                *arg = arg.replace("%n%", &self.title);
                 *arg = arg.replace("%i%", &self.file_path);
                 *arg = arg.replace("%o%", &self.output_path);
                 *arg = arg.replace("%t%", &self.get_temp_path());
                 *arg = arg.replace("%log%", "");
             }
 
+            // During a dry run, the command will only be logged.
+            if params.misc.dry_run {
+                logger::log(format!("[DRY RUN] \"{path}\" {}", args.join(" ")), true);
+                continue;
+            }
+
             // Run the command and show the results.
-            match Command::new(path).args(args).output() {
-                Ok(o) => {
+            let success = match Command::new(path).args(args).output() {
+                Ok(o) if o.status.success() => {
                     logger::log(
                         "The command was successfully executed and yielded the following output:",
                         false,
                     );
                     logger::log_output_lines(&String::from_utf8_lossy(&o.stdout), false);
+                    true
+                }
+                Ok(o) => {
+                    logger::log(
+                        format!(
+                            "The command exited with status {} and yielded the following output:",
+                            o.status
+                        ),
+                        true,
+                    );
+                    logger::log_output_lines(&String::from_utf8_lossy(&o.stdout), false);
+                    logger::log_output_lines(&String::from_utf8_lossy(&o.stderr), false);
+                    false
                 }
                 Err(e) => {
                     logger::log(
-                        format!("The command was not successfully executed and yielded the following output: {e:?}"), false
+                        format!("The command was not successfully executed and yielded the following output: {e:?}"), true
                     );
+                    false
                 }
+            };
+
+            if !success && !continue_on_error {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Apply any metadata substitutions to the title of the media file, based on the tracks that were kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    fn apply_metadata_substitutions(&mut self, params: &UnifiedParams) {
+        for substitution in params.misc.metadata_substitutions.iter().flatten() {
+            if !substitution.is_match(&self.media.tracks) {
+                continue;
             }
+
+            let video = substitution.find_track(&self.media.tracks, TrackType::Video);
+            let audio = substitution.find_track(&self.media.tracks, TrackType::Audio);
+
+            let title = substitution
+                .template
+                .replace("%n%", &self.title)
+                .replace(
+                    "%width%",
+                    &video.map(|t| t.width.to_string()).unwrap_or_default(),
+                )
+                .replace(
+                    "%height%",
+                    &video.map(|t| t.height.to_string()).unwrap_or_default(),
+                )
+                .replace(
+                    "%audio_codec%",
+                    &audio.map(|t| format!("{:?}", t.codec)).unwrap_or_default(),
+                )
+                .replace(
+                    "%channels%",
+                    &audio.map(|t| t.channels.to_string()).unwrap_or_default(),
+                );
+
+            logger::log(
+                format!("The title '{}' was substituted with '{title}'.", self.title),
+                false,
+            );
+            self.title = title;
+        }
+    }
+
+    /// Build the title to be embedded within the media file, based on the title template.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The title of the media file.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the title to be embedded within the media file.
+    fn embedded_title(&self, title: &str, params: &UnifiedParams) -> String {
+        match &params.misc.file_title_template {
+            Some(template) => template
+                .replace("%index%", self.index.as_deref().unwrap_or_default())
+                .replace("%n%", title)
+                .trim()
+                .to_string(),
+            None => title.to_string(),
         }
     }
 
@@ -1255,19 +2467,65 @@ impl MediaFile {
     /// * `out_path` - The path to the expected location of the output media file.
     /// * `title` - The title of the media file.
     /// * `params` - The [`UnifiedParams`] to be applied to the media file.
-    pub fn remux_file(&mut self, out_path: &str, title: &str, params: &UnifiedParams) -> bool {
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] if the output file could not be muxed.
+    pub fn remux_file(
+        &mut self,
+        out_path: &str,
+        title: &str,
+        params: &UnifiedParams,
+    ) -> Result<(), ProcessError> {
         logger::log("Remuxing media file... ", false);
 
+        // The embedded title may differ from the title used within the output file name.
+        let title = &self.embedded_title(title, params);
+
         // The output file path.
         self.muxing_args.push("-o".to_string());
         self.muxing_args.push(out_path.to_string());
 
         // The title of the media file, if needed.
-        if let Some(b) = params.misc.set_file_title {
-            if b {
-                self.muxing_args.push("--title".to_string());
-                self.muxing_args.push(title.to_string());
-            }
+        if params.misc.set_file_title.unwrap_or(false) {
+            self.muxing_args.push("--title".to_string());
+            self.muxing_args.push(title.to_string());
+        } else if params.misc.clear_file_title.unwrap_or(false) {
+            self.muxing_args.push("--title".to_string());
+            self.muxing_args.push(String::new());
+        }
+
+        // Create a WebM file, if required.
+        if params.misc.output_container == Some(Container::Webm) {
+            self.muxing_args.push("--webm".to_string());
+        }
+
+        // Split the output file, if required.
+        if let Some(split) = &params.misc.split {
+            self.muxing_args.push("--split".to_string());
+            self.muxing_args.push(split.as_mkvmerge_argument());
+        }
+
+        // Disable the track statistics tags, if required.
+        if params.misc.disable_track_statistics_tags.unwrap_or(false) {
+            self.muxing_args
+                .push("--disable-track-statistics-tags".to_string());
+        }
+
+        // Make the output deterministic, if required. The seed is derived from the input
+        // file name so that the segment UIDs remain unique between files.
+        if params.misc.reproducible.unwrap_or(false) {
+            let name = utils::get_file_name(&self.file_path).unwrap_or_default();
+            self.muxing_args.push("--no-date".to_string());
+            self.muxing_args.push("--deterministic".to_string());
+            self.muxing_args
+                .push(format!("{:016x}", utils::fnv1a_hash(name.as_bytes())));
+        }
+
+        // Any extra arguments are placed after the global options and before the input files,
+        // so they will be treated as global options.
+        if let Some(args) = &params.misc.extra_mkvmerge_args {
+            self.muxing_args.extend(args.iter().cloned());
         }
 
         // Apply the track muxing arguments.
@@ -1277,17 +2535,28 @@ impl MediaFile {
         self.apply_attachment_mux_params(params);
 
         // Apply the chapter muxing arguments, if needed.
-        if params.chapters.import_from_original || params.chapters.create_if_not_present {
+        if params.chapters.import_from_original
+            || params.chapters.import_from_file.is_some()
+            || params.chapters.create_if_not_present
+        {
             self.apply_chapters_mux_params(params);
         }
 
         // Apply the tag muxing arguments, if needed.
-        if params.misc.tags_path.is_some() {
-            self.apply_tag_mux_params(params);
+        if params.misc.tags_path.is_some() || params.misc.generate_tags.unwrap_or(false) {
+            self.apply_tag_mux_params(title, params);
         }
 
-        // Set the track order.
-        let order = (0..self.media.tracks.len())
+        // Set the track order. Each track is a separate input file, so by default the
+        // tracks will be written in the order in which they were added. The original
+        // order of the tracks within the source file may be used instead, if required.
+        let mut indices: Vec<usize> = (0..self.media.tracks.len()).collect();
+        if params.misc.preserve_track_order.unwrap_or(false) {
+            indices.sort_by_key(|&i| self.media.tracks[i].id);
+        }
+
+        let order = indices
+            .iter()
             .map(|i| format!("{i}:0"))
             .collect::<Vec<String>>()
             .join(",");
@@ -1296,31 +2565,43 @@ impl MediaFile {
         self.muxing_args.push(order);
 
         // Run the MKV merge process.
-        let success = match mkvtoolnix::run_merge(&self.get_temp_path(), &self.muxing_args) {
-            0 | 1 => {
+        let (code, warnings) =
+            mkvtoolnix::run_merge(&self.get_temp_path(), &self.muxing_args, &params.misc);
+
+        let success = match code {
+            0 => {
                 logger::log("Remuxing complete!", false);
                 true
             }
+            1 => {
+                logger::log("Remuxing complete, with warnings.", false);
+                true
+            }
             2 => {
                 logger::log("Remuxing failed!", false);
                 false
             }
             _ => true,
         };
+        self.add_warnings(warnings);
 
         // Output the mkvmerge parameters, if the debug flag is set.
-        if DEBUG_PARAMS {
-            logger::log(
+        if params.misc.debug_commands.unwrap_or(false) {
+            logger::log_level(
+                Level::Info,
                 format!(
-                    "[INFO] mkvmerge command line: \"{}\" {}",
+                    "mkvmerge command line: \"{}\" {}",
                     mkvtoolnix::get_exe("mkvmerge"),
                     self.muxing_args.join(" ")
                 ),
-                false,
             );
         }
 
-        success
+        if success {
+            Ok(())
+        } else {
+            Err(ProcessError::MuxFailed)
+        }
     }
 
     /// Check whether a given track should be kept in the final file.
@@ -1347,40 +2628,62 @@ impl MediaFile {
         }
 
         // The panic should never happen since the cases are all dealt with above.
-        let predicate = match track_type {
-            TrackType::Audio => &params.audio_tracks.predicate,
-            TrackType::Subtitle => &params.subtitle_tracks.predicate,
-            TrackType::Video => &params.video_tracks.predicate,
-            _ => panic!(),
-        };
-
-        // The panic should never happen since the cases are all dealt with above.
-        let tracks_to_retain = match track_type {
-            TrackType::Audio => &params.audio_tracks.total_to_retain,
-            TrackType::Subtitle => &params.subtitle_tracks.total_to_retain,
-            TrackType::Video => &params.video_tracks.total_to_retain,
-            _ => panic!(),
-        };
+        let (predicate, tracks_to_retain, mode) =
+            MediaFile::track_retention_params(track_type, params).unwrap();
 
         // Is a track limiter in place, and have we reached the target number of tracks?
-        if let Some(target) = tracks_to_retain {
-            let retained = self.track_type_counter.get(track_type).unwrap_or(&0);
-            if retained >= target {
-                return false;
+        // A minimum target doesn't limit the number of tracks that may be retained.
+        match tracks_to_retain {
+            TrackRetention::All => {}
+            TrackRetention::Count(target) => {
+                let retained = self.track_type_counter.get(track_type).unwrap_or(&0);
+                if mode != RetainMode::AtLeast && *retained >= target {
+                    return false;
+                }
             }
+            TrackRetention::None => return false,
         }
 
-        // Note: that the filters are validated so the unwraps are safe here.
-        let track = &self.media.tracks[index];
-        match &predicate {
-            TrackPredicate::Index(i) => i.is_match(index - 1),
-            TrackPredicate::Language(l) => l.is_match(&track.language),
-            TrackPredicate::Title(t) => t.is_match(&track.title),
-            TrackPredicate::None => true,
+        // Check the track against the predicate, including any nested predicates.
+        predicate.is_track_match(index, &self.media.tracks[index])
+    }
+
+    /// Get the predicate and retention parameters for a given track type.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_type` - The type of track.
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// An option containing the [`TrackPredicate`], the [`TrackRetention`] and the [`RetainMode`],
+    /// or `None` if the track type can't be filtered in this way.
+    fn track_retention_params<'a>(
+        track_type: &TrackType,
+        params: &'a UnifiedParams,
+    ) -> Option<(&'a TrackPredicate, TrackRetention, RetainMode)> {
+        match track_type {
+            TrackType::Audio => Some((
+                &params.audio_tracks.predicate,
+                params.audio_tracks.total_to_retain,
+                params.audio_tracks.retain_mode.unwrap_or_default(),
+            )),
+            TrackType::Subtitle => Some((
+                &params.subtitle_tracks.predicate,
+                params.subtitle_tracks.total_to_retain,
+                params.subtitle_tracks.retain_mode.unwrap_or_default(),
+            )),
+            TrackType::Video => Some((
+                &params.video_tracks.predicate,
+                params.video_tracks.total_to_retain,
+                params.video_tracks.retain_mode.unwrap_or_default(),
+            )),
+            _ => None,
         }
     }
 
-    /// Validate whether the number of tracks met the specified target, if applicable.
+    /// Validate whether enough tracks match the predicates to meet the specified targets, if applicable.
     ///
     /// # Arguments
     ///
@@ -1388,28 +2691,119 @@ impl MediaFile {
     ///
     /// # Returns
     ///
-    /// A boolean, true if the filtering met the track targets (if applicable), false otherwise.
-    fn validate_filter_targets(&self, params: &UnifiedParams) -> bool {
-        let mut success = true;
+    /// A result, which will contain the [`ProcessError`] for the first track target that can't be met (if applicable).
+    fn validate_available_tracks(&self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        let mut result = Ok(());
         for target_type in [TrackType::Audio, TrackType::Subtitle, TrackType::Video] {
-            let target = match target_type {
-                TrackType::Audio => params.audio_tracks.total_to_retain,
-                TrackType::Subtitle => params.subtitle_tracks.total_to_retain,
-                TrackType::Video => params.video_tracks.total_to_retain,
-                _ => None,
+            let Some((predicate, TrackRetention::Count(target), mode)) =
+                MediaFile::track_retention_params(&target_type, params)
+            else {
+                continue;
             };
 
-            if target.is_none() {
+            // An upper bound can always be met.
+            if mode == RetainMode::AtMost {
                 continue;
             }
 
-            if self
+            let found = self
+                .media
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(i, track)| {
+                    track.track_type == target_type && predicate.is_track_match(*i, track)
+                })
+                .count();
+
+            if found < target {
+                logger::log_level(
+                    Level::Warn,
+                    format!(
+                        "Requested {target} {target_type} track(s), but only {found} matching track(s) were found in file '{}'. The file will be skipped.",
+                        self.file_path
+                    ),
+                );
+
+                if result.is_ok() {
+                    result = Err(ProcessError::TrackTargetUnmet {
+                        track_type: target_type,
+                        target,
+                        found,
+                    });
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Validate whether the codecs of the tracks are supported by the output container.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] listing every track that can't be stored within the output container.
+    fn validate_container_codecs(&self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        let container = params.misc.output_container.unwrap_or_default();
+
+        // The codecs of any converted tracks will have been updated by this point.
+        let mut tracks = Vec::new();
+        for track in &self.media.tracks {
+            if !container.supports_codec(&track.codec) {
+                logger::log_level(
+                    Level::Warn,
+                    format!(
+                        "The {} track {} has codec '{:?}', which is not supported by the {container:?} container.",
+                        track.track_type, track.id, track.codec
+                    ),
+                );
+                tracks.push((track.id, track.codec.clone()));
+            }
+        }
+
+        if tracks.is_empty() {
+            Ok(())
+        } else {
+            logger::log_level(
+                Level::Warn,
+                format!(
+                    "{} track{} can't be stored within the {container:?} container. The file will be skipped.",
+                    tracks.len(),
+                    if tracks.len() != 1 { "s" } else { "" }
+                ),
+            );
+            Err(ProcessError::UnsupportedCodecs { container, tracks })
+        }
+    }
+
+    /// Validate whether the number of tracks met the specified target, if applicable.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The [`UnifiedParams`] to be applied to the media file.
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain the [`ProcessError`] for the first track target that was not met (if applicable).
+    fn validate_filter_targets(&self, params: &UnifiedParams) -> Result<(), ProcessError> {
+        let mut result = Ok(());
+        for target_type in [TrackType::Audio, TrackType::Subtitle, TrackType::Video] {
+            let Some((_, TrackRetention::Count(target), mode)) =
+                MediaFile::track_retention_params(&target_type, params)
+            else {
+                continue;
+            };
+
+            let retained = self
                 .track_type_counter
                 .get(&target_type)
                 .cloned()
-                .unwrap_or_default()
-                != target.unwrap()
-            {
+                .unwrap_or_default();
+            if !mode.is_satisfied(retained, target) {
                 logger::log(
                     format!(
                         "Filtered track target for type {target_type} was different than expected for file {}.",
@@ -1418,11 +2812,17 @@ impl MediaFile {
                     false,
                 );
 
-                success = false;
+                if result.is_ok() {
+                    result = Err(ProcessError::TrackTargetUnmet {
+                        track_type: target_type,
+                        target,
+                        found: retained,
+                    });
+                }
             }
         }
 
-        success
+        result
     }
 }
 
@@ -1455,6 +2855,22 @@ pub struct MediaFileTrack {
     #[serde(rename = "Channels", deserialize_with = "string_to_u32", default)]
     pub channels: u32,
 
+    /// The sample rate of the track, in hertz. Only applicable to audio tracks.
+    #[serde(
+        rename = "SamplingRate",
+        deserialize_with = "first_string_to_u32_or_zero",
+        default
+    )]
+    pub sample_rate: u32,
+
+    /// The bitrate of the track, in bits per second. This will be zero if the bitrate is unknown.
+    #[serde(
+        rename = "BitRate",
+        deserialize_with = "string_to_u32_or_zero",
+        default
+    )]
+    pub bitrate: u32,
+
     /// The delay of the tracks, in milliseconds.
     #[serde(rename = "Delay", deserialize_with = "second_string_to_ms", default)]
     pub delay: i32,
@@ -1500,9 +2916,26 @@ pub struct MediaFileTrack {
     /// The index of the file to which this track belongs.
     #[serde(skip)]
     pub file_id: usize,
+
+    /// Was the track converted during processing?
+    #[serde(skip)]
+    pub converted: bool,
 }
 
 impl MediaFileTrack {
+    /// Check whether the track appears to duplicate another track.
+    /// Tracks are considered to be duplicates if they have the same type, language, codec and channel count.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other track.
+    pub fn is_duplicate_of(&self, other: &MediaFileTrack) -> bool {
+        self.track_type == other.track_type
+            && self.language == other.language
+            && self.codec == other.codec
+            && self.channels == other.channels
+    }
+
     /// Get the output name for this track.
     pub fn get_out_file_name(&self) -> String {
         let ext = MediaFileTrack::get_extension_from_codec(&self.codec);
@@ -1515,7 +2948,7 @@ impl MediaFileTrack {
         // Determine the output file name.
         format!(
             "{}\\tracks\\{}",
-            utils::join_path_segments(&paths::PATHS.temp, &[self.file_id.to_string()]),
+            utils::join_path_segments(&paths::temp_dir(), &[self.file_id.to_string()]),
             self.get_out_file_name()
         )
     }
@@ -1537,20 +2970,24 @@ impl MediaFileTrack {
             Codec::Aac => "aac",
             Codec::Ac3 => "ac3",
             Codec::AdvancedSsa => "ass",
+            Codec::Av1 => "ivf",
             Codec::Dts => "dts",
             Codec::Eac3 => "eac3",
             Codec::FfV1 => "ffv1",
             Codec::Flac => "flac",
             Codec::H264 => "h264",
             Codec::Hevc => "hevc",
+            Codec::Mp2 => "mp2",
             Codec::Mp3 => "mp3",
             Codec::Opus => "opus",
             Codec::SubStationAlpha => "ssa",
             Codec::DvbSubtitle | Codec::Hdmv | Codec::SubtitleTextUtf8 => "srt",
             Codec::SubtitleBitmap => "bmp",
             Codec::Unknown => "unknown",
+            Codec::Vorbis => "ogg",
             Codec::Vp8 => "vp8",
             Codec::Vp9 => "vp9",
+            Codec::WavPack => "wv",
             Codec::WebVtt => "vtt",
         };
 
@@ -1596,17 +3033,21 @@ where
         "V_VP8" => Codec::Vp8,
         "V_VP9" => Codec::Vp9,
         "V_FFV1" => Codec::FfV1,
+        "V_AV1" => Codec::Av1,
 
         // Audio codecs.
+        "A_MPEG/L2" => Codec::Mp2,
         "A_MPEG/L3" => Codec::Mp3,
         "A_AC3" | "A_AC3/BSID9" | "A_AC3/BSID10" => Codec::Ac3,
         "A_DTS" | "A_DTS/EXPRESS" | "A_DTS/LOSSLESS" => Codec::Dts,
-        "A_VORBIS" | "A_OPUS" => Codec::Opus,
+        "A_OPUS" => Codec::Opus,
+        "A_VORBIS" => Codec::Vorbis,
         "A_FLAC" => Codec::Flac,
         "A_AAC/MPEG2/MAIN" | "A_AAC/MPEG2/LC" | "A_AAC/MPEG2/LC/SBR" | "A_AAC/MPEG2/SSR"
         | "A_AAC/MPEG4/MAIN" | "A_AAC/MPEG4/LC" | "A_AAC/MPEG4/LC/SBR" | "A_AAC/MPEG4/SSR"
         | "A_AAC/MPEG4/LTP" | "A_AAC-1" | "A_AAC-2" => Codec::Aac,
         "A_EAC3" => Codec::Eac3,
+        "A_WAVPACK4" => Codec::WavPack,
 
         // Subtitle codecs.
         "S_TEXT/UTF8" => Codec::SubtitleTextUtf8,
@@ -1617,13 +3058,13 @@ where
         "S_HDMV/PGS" | "S_HDMV/TEXTST" => Codec::Hdmv,
         "S_TEXT/WEBVTT" => Codec::WebVtt,
 
-        // Unknown codecs.
+        // Unknown codecs. These will be skipped, rather than aborting the processing entirely.
         _ => {
-            logger::log(
-                format!("[WARN] Unexpected codec ID when parsing MKV file: {string}"),
-                true,
+            logger::log_level(
+                Level::Warn,
+                format!("Unexpected codec ID when parsing MKV file: {string}"),
             );
-            panic!()
+            Codec::Unknown
         }
     };
 
@@ -1665,17 +3106,28 @@ where
     D: Deserializer<'de>,
 {
     let string = String::deserialize(deserializer)?;
+    let trimmed = string.trim();
+
+    // An empty value indicates that there is no delay.
+    if trimmed.is_empty() {
+        return Ok(0);
+    }
 
     // The string is defined in terms of seconds.
-    // We will therefore first attempt to parse this value as a f32.
-    match string.parse::<f32>() {
-        Ok(n) => {
+    // We will therefore first attempt to parse this value as a f64, which
+    // also handles scientific notation. Failing that, we will attempt to
+    // handle values that use a comma as the decimal separator.
+    match trimmed
+        .parse::<f64>()
+        .or_else(|_| trimmed.replace(',', ".").parse::<f64>())
+    {
+        Ok(n) if n.is_finite() => {
             // The number must be multiplied by 1000 to give the delay in milliseconds.
-            Ok((n * 1000.0) as i32)
+            Ok((n * 1000.0).round() as i32)
         }
-        Err(_) => Err(de::Error::invalid_value(
+        _ => Err(de::Error::invalid_value(
             Unexpected::Str(&string),
-            &"expected an integer",
+            &"expected a number",
         )),
     }
 }
@@ -1695,6 +3147,30 @@ where
     }
 }
 
+fn first_string_to_u32_or_zero<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let string = String::deserialize(deserializer)?;
+
+    // Some values, such as the sample rate of a HE-AAC track, may list several
+    // values (e.g. "48000 / 24000"). Only the first value is used.
+    Ok(string
+        .split('/')
+        .next()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or_default())
+}
+
+fn string_to_u32_or_zero<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let string = String::deserialize(deserializer)?;
+
+    Ok(string.parse::<u32>().unwrap_or_default())
+}
+
 #[allow(unused)]
 fn yes_no_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
@@ -1711,3 +3187,249 @@ where
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion_params::unified::tests::params_from_json;
+
+    /// Get a command that will always exit with a status of 1.
+    fn failing_command() -> Vec<&'static str> {
+        if cfg!(windows) {
+            vec!["C:\\Windows\\System32\\cmd.exe", "/C", "exit 1"]
+        } else {
+            vec!["/bin/sh", "-c", "exit 1"]
+        }
+    }
+
+    /// Create a media file without any tracks.
+    fn empty_media_file() -> MediaFile {
+        serde_json::from_str(r#"{ "media": { "track": [] } }"#).unwrap()
+    }
+
+    #[test]
+    fn failing_run_command_aborts_the_file() {
+        let params = params_from_json(serde_json::json!({
+            "misc": { "run": [{ "pre_extract": failing_command() }] }
+        }));
+
+        let result = empty_media_file().process("out.mkv", "Title", &params);
+        assert!(matches!(
+            result,
+            Err(ProcessError::CommandFailed(RunCommandType::PreExtract))
+        ));
+    }
+
+    #[test]
+    fn run_commands_only_run_for_their_own_type() {
+        let params = params_from_json(serde_json::json!({
+            "misc": { "run": [{ "post_convert": failing_command() }] }
+        }));
+
+        let media = empty_media_file();
+        assert!(media.run_commands(RunCommandType::PreConvert, &params));
+        assert!(!media.run_commands(RunCommandType::PostConvert, &params));
+    }
+
+    #[test]
+    fn known_codec_ids_are_mapped() {
+        for (id, codec) in [
+            ("V_MPEG4/ISO/AVC", Codec::H264),
+            ("A_AAC/MPEG4/LC/SBR", Codec::Aac),
+            ("A_DTS/LOSSLESS", Codec::Dts),
+            ("S_HDMV/PGS", Codec::Hdmv),
+        ] {
+            assert_eq!(string_to_codec_enum(serde_json::json!(id)).unwrap(), codec);
+        }
+    }
+
+    #[test]
+    fn unknown_codec_ids_are_mapped_to_unknown() {
+        for id in ["V_UNCOMPRESSED", "A_TRUEHD", ""] {
+            assert_eq!(
+                string_to_codec_enum(serde_json::json!(id)).unwrap(),
+                Codec::Unknown
+            );
+        }
+    }
+
+    #[test]
+    fn delays_are_converted_to_milliseconds() {
+        for (delay, ms) in [
+            ("0.042", 42),
+            ("-1.5", -1500),
+            ("4.2e-2", 42),
+            ("  2  ", 2000),
+        ] {
+            assert_eq!(second_string_to_ms(serde_json::json!(delay)).unwrap(), ms);
+        }
+    }
+
+    #[test]
+    fn empty_delays_are_zero() {
+        for delay in ["", "   "] {
+            assert_eq!(second_string_to_ms(serde_json::json!(delay)).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn comma_decimal_delays_are_parsed() {
+        assert_eq!(second_string_to_ms(serde_json::json!("0,042")).unwrap(), 42);
+        assert_eq!(
+            second_string_to_ms(serde_json::json!("-1,5")).unwrap(),
+            -1500
+        );
+    }
+
+    #[test]
+    fn non_numeric_delays_are_rejected() {
+        for delay in ["abc", "1.2.3", "NaN", "inf"] {
+            assert!(
+                second_string_to_ms(serde_json::json!(delay)).is_err(),
+                "{delay}"
+            );
+        }
+    }
+
+    #[test]
+    fn duplicate_tracks_are_skipped_when_deduping() {
+        let audio = |language: &str, channels: &str| {
+            serde_json::json!({
+                "@type": "Audio",
+                "CodecID": "A_AAC-2",
+                "Language": language,
+                "Channels": channels,
+            })
+        };
+        let tracks = serde_json::json!([
+            audio("eng", "2"),
+            audio("eng", "2"),
+            audio("eng", "6"),
+            audio("jpn", "2"),
+        ]);
+
+        for (dedupe, expected) in [(false, 4), (true, 3)] {
+            let mut media: MediaFile =
+                serde_json::from_value(serde_json::json!({ "media": { "track": tracks } }))
+                    .unwrap();
+            let params = params_from_json(serde_json::json!({
+                "misc": { "dedupe_tracks": dedupe }
+            }));
+
+            media.filter_tracks(&params).unwrap();
+            assert_eq!(media.media.tracks.len(), expected, "dedupe: {dedupe}");
+        }
+    }
+
+    #[test]
+    fn tracks_differing_in_any_compared_field_are_not_duplicates() {
+        let track = |track_type: &str, codec: &str, language: &str, channels: &str, title: &str| {
+            serde_json::from_value::<MediaFileTrack>(serde_json::json!({
+                "@type": track_type,
+                "CodecID": codec,
+                "Language": language,
+                "Channels": channels,
+                "Title": title,
+            }))
+            .unwrap()
+        };
+
+        let original = track("Audio", "A_AAC-2", "eng", "2", "Main");
+        assert!(original.is_duplicate_of(&track("Audio", "A_AAC-2", "eng", "2", "Other")));
+        assert!(!original.is_duplicate_of(&track("Text", "A_AAC-2", "eng", "2", "Main")));
+        assert!(!original.is_duplicate_of(&track("Audio", "A_AAC-2", "jpn", "2", "Main")));
+        assert!(!original.is_duplicate_of(&track("Audio", "A_AC3", "eng", "2", "Main")));
+        assert!(!original.is_duplicate_of(&track("Audio", "A_AAC-2", "eng", "6", "Main")));
+    }
+
+    #[test]
+    fn attachments_with_identical_contents_are_skipped() {
+        let dir = std::env::temp_dir()
+            .join("bulk-mkv-manipulation-tests")
+            .join(format!("attachments-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let files = [
+            ("font.ttf", "font data"),
+            ("copy of font.ttf", "font data"),
+            ("other.ttf", "other font data"),
+        ];
+        let paths: Vec<_> = files
+            .iter()
+            .map(|(name, contents)| {
+                let path = dir.join(name);
+                fs::write(&path, contents).unwrap();
+                path.display().to_string()
+            })
+            .collect();
+
+        let mut media = empty_media_file();
+        for path in &paths {
+            media.add_attachment_if_matching(path, &None, false);
+        }
+
+        let attached: Vec<_> = media
+            .muxing_args
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| *a == "--attach-file")
+            .map(|(i, _)| &media.muxing_args[i + 1])
+            .collect();
+        assert_eq!(attached, [&paths[0], &paths[2]]);
+
+        _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extractions_are_skipped_once_another_has_failed() {
+        let mut params = params_from_json(serde_json::json!({
+            "chapters": { "import_from_original": true, "create_if_not_present": false }
+        }));
+        params.misc.dry_run = true;
+
+        let media = empty_media_file();
+        assert!(media.extract_chapters(&params));
+
+        media.extraction_aborted.store(true, Ordering::SeqCst);
+        assert!(!media.extract_chapters(&params));
+    }
+
+    #[test]
+    fn compound_sample_rates_use_the_first_value() {
+        for (rate, expected) in [
+            ("48000", 48000),
+            ("48000 / 24000", 48000),
+            ("", 0),
+            ("n/a", 0),
+        ] {
+            let track: MediaFileTrack = serde_json::from_value(serde_json::json!({
+                "@type": "Audio",
+                "SamplingRate": rate,
+            }))
+            .unwrap();
+            assert_eq!(track.sample_rate, expected, "{rate}");
+        }
+    }
+
+    #[test]
+    fn clearing_temp_directories_removes_partial_output() {
+        let media = empty_media_file();
+        assert!(media.init_temp_directory());
+
+        let partial =
+            utils::join_path_segments(&media.get_temp_for_output_type("tracks"), &["partial.mka"]);
+        let kept = utils::join_path_segments(
+            &media.get_temp_for_output_type("attachments"),
+            &["kept.ttf"],
+        );
+        fs::write(&partial, "partial").unwrap();
+        fs::write(&kept, "kept").unwrap();
+
+        assert!(media.clear_temp_directories(&["tracks", "chapters"]));
+        assert!(!Path::new(&partial).exists());
+        assert!(Path::new(&media.get_temp_for_output_type("tracks")).is_dir());
+        assert!(Path::new(&kept).exists());
+
+        _ = fs::remove_dir_all(media.get_temp_path());
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{logger, paths};
+use crate::{conversion_params::unified::MiscParams, logger, paths, utils};
 
-use std::{path::Path, process::Command};
+use std::{
+    io,
+    path::Path,
+    process::{Command, Output},
+};
 
 const FAIL_ERROR_CODE: i32 = 2;
+const WARNING_CODE: i32 = 1;
 
 pub fn get_exe(exe: &str) -> String {
     Path::new(&paths::PATHS.mkvtoolnix)
@@ -19,37 +24,118 @@ pub fn get_exe(exe: &str) -> String {
 /// * `out_path` - The output file path.
 /// * `arg_type` - The type of action to be performed.
 /// * `args` - A list of arguments to be passed to the extractor.
-pub fn run_extract(in_path: &str, out_path: &str, arg_type: &str, args: &[String]) -> i32 {
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
+///
+/// # Returns
+///
+/// A tuple containing the exit code of the process and any warnings that it issued.
+pub fn run_extract(
+    in_path: &str,
+    out_path: &str,
+    arg_type: &str,
+    args: &[String],
+    misc: &MiscParams,
+) -> (i32, Vec<String>) {
+    run_extract_multiple(in_path, out_path, &[(arg_type, args.to_vec())], misc)
+}
+
+/// Run the MKV extract process, performing several types of action with a single process.
+/// The output of each type of action will be written to the subdirectory of the same name.
+///
+/// # Arguments
+///
+/// * `in_path` - The input file path.
+/// * `out_path` - The output file path.
+/// * `actions` - A list of the types of action to be performed, along with the arguments for each action.
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
+///
+/// # Returns
+///
+/// A tuple containing the exit code of the process and any warnings that it issued.
+pub fn run_extract_multiple(
+    in_path: &str,
+    out_path: &str,
+    actions: &[(&str, Vec<String>)],
+    misc: &MiscParams,
+) -> (i32, Vec<String>) {
     let path = get_exe("mkvextract");
+    let args = get_extract_args(actions);
 
-    let output = Command::new(path)
-        .arg(in_path)
-        .arg(arg_type)
-        .args(args)
-        .current_dir(format!("{out_path}/{arg_type}"))
-        .output();
-
-    let result = match &output {
-        Ok(o) => {
-            if let Some(code) = o.status.code() {
-                code
-            } else {
-                FAIL_ERROR_CODE
-            }
-        }
-        Err(_) => FAIL_ERROR_CODE,
-    };
+    if misc.dry_run {
+        logger::log(
+            format!("[DRY RUN] \"{path}\" \"{in_path}\" {}", args.join(" ")),
+            true,
+        );
+        return (0, Vec::new());
+    }
+
+    let (result, output) = utils::run_with_retries(
+        "MKV Extract",
+        misc,
+        |code| code == FAIL_ERROR_CODE,
+        || {
+            let output = Command::new(&path)
+                .arg(in_path)
+                .args(&args)
+                .current_dir(out_path)
+                .output();
+
+            get_result(&output)
+        },
+    );
 
     if result == FAIL_ERROR_CODE {
         logger::log(
             " MKV Extract was not successfully executed and yielded the following output:",
             false,
         );
-        let out = output.unwrap();
-        logger::log_output_lines(&String::from_utf8_lossy(&out.stderr), false);
+        logger::log_output_lines(&output, false);
+    }
+
+    let warnings = if result == WARNING_CODE {
+        get_warnings("MKV Extract", &output)
+    } else {
+        Vec::new()
+    };
+
+    (result, warnings)
+}
+
+/// Build the mkvextract argument list for several types of action.
+///
+/// # Arguments
+///
+/// * `actions` - A list of the types of action to be performed, along with the arguments for each action.
+///
+/// # Returns
+///
+/// A list of the arguments, with each output path placed within the subdirectory named after its type of action.
+pub fn get_extract_args(actions: &[(&str, Vec<String>)]) -> Vec<String> {
+    let mut args = Vec::new();
+    for (arg_type, action_args) in actions {
+        args.push(arg_type.to_string());
+        args.extend(action_args.iter().map(|a| get_extract_spec(arg_type, a)));
     }
 
-    result
+    args
+}
+
+/// Place the output path of a mkvextract argument within the subdirectory named after its type of action.
+///
+/// # Arguments
+///
+/// * `arg_type` - The type of action to be performed.
+/// * `spec` - The argument, either in the `ID:name` form or a plain output file name.
+///
+/// # Returns
+///
+/// The argument, with the subdirectory added to the output path.
+fn get_extract_spec(arg_type: &str, spec: &str) -> String {
+    // Track and attachment arguments are given as ID:name, chapters only as a file name.
+    match spec.split_once(':') {
+        Some((id, name)) if arg_type != "chapters" => format!("{id}:{arg_type}/{name}"),
+        _ => format!("{arg_type}/{spec}"),
+    }
 }
 
 /// Run the MKV merge process.
@@ -58,29 +144,139 @@ pub fn run_extract(in_path: &str, out_path: &str, arg_type: &str, args: &[String
 ///
 /// * `base_dir` - The base directory for the process.
 /// * `args` - A list of arguments to be passed to the extractor.
-pub fn run_merge(base_dir: &str, args: &[String]) -> i32 {
+/// * `misc` - The [`MiscParams`] to be applied, such as the dry run and retry options.
+///
+/// # Returns
+///
+/// A tuple containing the exit code of the process and any warnings that it issued.
+pub fn run_merge(base_dir: &str, args: &[String], misc: &MiscParams) -> (i32, Vec<String>) {
     let path = get_exe("mkvmerge");
 
-    let output = Command::new(path).args(args).current_dir(base_dir).output();
-    let result = match &output {
-        Ok(o) => {
-            if let Some(code) = o.status.code() {
-                code
-            } else {
-                FAIL_ERROR_CODE
-            }
-        }
-        Err(_) => FAIL_ERROR_CODE,
-    };
+    if misc.dry_run {
+        logger::log(format!("[DRY RUN] \"{path}\" {}", args.join(" ")), true);
+        return (0, Vec::new());
+    }
+
+    let (result, output) = utils::run_with_retries(
+        "MKV Merge",
+        misc,
+        |code| code == FAIL_ERROR_CODE,
+        || {
+            get_result(
+                &Command::new(&path)
+                    .args(args)
+                    .current_dir(base_dir)
+                    .output(),
+            )
+        },
+    );
 
     if result == FAIL_ERROR_CODE {
         logger::log(
             " MKV Merge was not successfully executed and yielded the following output:",
             false,
         );
-        let out = output.unwrap();
-        logger::log_output_lines(&String::from_utf8_lossy(&out.stderr), false);
+        logger::log_output_lines(&output, false);
+    }
+
+    let warnings = if result == WARNING_CODE {
+        get_warnings("MKV Merge", &output)
+    } else {
+        Vec::new()
+    };
+
+    (result, warnings)
+}
+
+/// Get the exit code and output of a MkvToolNix process.
+///
+/// # Arguments
+///
+/// * `output` - The result of running the process.
+///
+/// # Returns
+///
+/// A tuple containing the exit code and the combined standard and error output of the process.
+fn get_result(output: &io::Result<Output>) -> (i32, String) {
+    match output {
+        // MkvToolNix writes its warnings and errors to the standard output.
+        Ok(o) => (
+            o.status.code().unwrap_or(FAIL_ERROR_CODE),
+            format!(
+                "{}{}",
+                String::from_utf8_lossy(&o.stdout),
+                String::from_utf8_lossy(&o.stderr)
+            ),
+        ),
+        Err(e) => (FAIL_ERROR_CODE, format!("{e:?}")),
+    }
+}
+
+/// Get the warnings issued by a MkvToolNix process that completed with warnings.
+///
+/// # Arguments
+///
+/// * `name` - The name of the process.
+/// * `output` - The output of the process.
+///
+/// # Returns
+///
+/// A list of the warnings. If no warning lines could be identified, the whole of the output will be used.
+fn get_warnings(name: &str, output: &str) -> Vec<String> {
+    let lines: Vec<String> = output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect();
+
+    let mut warnings: Vec<String> = lines
+        .iter()
+        .filter(|l| l.starts_with("Warning:"))
+        .cloned()
+        .collect();
+    if warnings.is_empty() {
+        warnings = lines;
+    }
+
+    warnings.iter().map(|w| format!("{name}: {w}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_args_place_outputs_in_subdirectories() {
+        let args = get_extract_args(&[("tracks", vec!["3:audio_3_eng.ac3".to_string()])]);
+        assert_eq!(args, ["tracks", "3:tracks/audio_3_eng.ac3"]);
+
+        let args = get_extract_args(&[("attachments", vec!["1:font.ttf".to_string()])]);
+        assert_eq!(args, ["attachments", "1:attachments/font.ttf"]);
+
+        let args = get_extract_args(&[("chapters", vec!["chapters.xml".to_string()])]);
+        assert_eq!(args, ["chapters", "chapters/chapters.xml"]);
     }
 
-    result
+    #[test]
+    fn combined_extract_args_match_separate_args() {
+        let actions = [
+            (
+                "tracks",
+                vec![
+                    "0:video_0_und.hevc".to_string(),
+                    "1:audio_1_eng.ac3".to_string(),
+                ],
+            ),
+            ("attachments", vec!["1:cover.jpg".to_string()]),
+            ("chapters", vec!["chapters.xml".to_string()]),
+        ];
+
+        let separate: Vec<String> = actions
+            .iter()
+            .flat_map(|action| get_extract_args(std::slice::from_ref(action)))
+            .collect();
+
+        assert_eq!(get_extract_args(&actions), separate);
+    }
 }
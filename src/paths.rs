@@ -2,46 +2,166 @@ use crate::utils;
 
 use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
+use std::{env, fs, sync::RwLock};
 
 #[derive(Default, Deserialize, Serialize)]
 pub struct Paths {
+    /// The path to the MkvToolNix directory. If blank, the directory will be found via the PATH environment variable.
+    #[serde(default)]
     pub mkvtoolnix: String,
     pub temp: String,
+    /// The path to the FFMPEG executable. If blank, the executable will be found via the PATH environment variable.
+    #[serde(default)]
     pub ffmpeg: String,
+    /// The path to the MediaInfo CLI executable. If blank, the executable will be found via the PATH environment variable.
+    #[serde(default)]
     pub mediainfo: String,
     pub log: String,
+    /// Should the log file be appended to, rather than being truncated on each run?
+    pub log_append: Option<bool>,
 }
 
 lazy_static! {
     pub static ref PATHS: Paths = Paths::from_file();
+    /// The temporary directory for the current run, if it overrides the one in the paths file.
+    static ref TEMP_OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// Get the temporary directory for the current run.
+///
+/// # Returns
+///
+/// The overriding temporary directory, if one has been set, or the one in the paths file otherwise.
+pub fn temp_dir() -> String {
+    TEMP_OVERRIDE
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PATHS.temp.clone())
+}
+
+/// Set the temporary directory for the current run, overriding the one in the paths file.
+///
+/// # Arguments
+///
+/// * `dir` - The temporary directory, or `None` if the one in the paths file should be used.
+pub fn set_temp_dir(dir: Option<String>) {
+    *TEMP_OVERRIDE.write().unwrap() = dir;
 }
 
 impl Paths {
+    /// Load the paths file from the current directory, exiting the program if it could not be loaded.
+    /// When running the tests, a set of paths within the system temporary directory will be used instead.
     fn from_file() -> Paths {
-        use std::{env, fs};
+        match Paths::load() {
+            Ok(paths) => paths,
+            #[cfg(test)]
+            Err(_) => Paths::test_paths(),
+            #[cfg(not(test))]
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
 
-        let path = env::current_dir();
-        assert!(path.is_ok(), "Failed to get the current directory.");
+    /// Create the paths used when running the tests without a valid paths file.
+    #[cfg(test)]
+    fn test_paths() -> Paths {
+        let temp = env::temp_dir().join("bulk-mkv-manipulation-tests");
+        _ = fs::create_dir_all(&temp);
 
-        let mut path = path.unwrap();
+        Paths {
+            temp: temp.display().to_string(),
+            log: temp.join("test.log").display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Load the paths file from the current directory.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the [`Paths`] instance, or a description of the reason that it could not be loaded.
+    fn load() -> Result<Paths, String> {
+        let mut path =
+            env::current_dir().map_err(|e| format!("Failed to get the current directory: {e}"))?;
         path.push("paths.json");
-        assert!(
-            path.exists(),
-            "The path to the paths JSON file was invalid."
-        );
-
-        let json = fs::read_to_string(path).expect("failed to open paths data file");
-        let tools_result = serde_json::from_str::<Paths>(&json);
-        assert!(
-            tools_result.is_ok(),
-            "Error attempting to parse JSON data: {:?}",
-            tools_result.err()
-        );
-
-        let tools = tools_result.unwrap();
-        assert!(tools.check_paths(), "One or more paths were invalid.");
-
-        tools
+        if !path.exists() {
+            return Err(format!(
+                "The paths file could not be found at {}.",
+                path.display()
+            ));
+        }
+
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to open the paths file {}: {e}", path.display()))?;
+        let mut tools = serde_json::from_str::<Paths>(&json)
+            .map_err(|e| format!("The paths file {} is not valid: {e}", path.display()))?;
+
+        tools.expand_paths()?;
+        tools.resolve_tool_paths()?;
+
+        if !tools.check_paths() {
+            return Err("One or more paths within the paths file were invalid.".to_string());
+        }
+
+        Ok(tools)
+    }
+
+    /// Expand a leading tilde and any environment variables within each of the paths.
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain a description of the first variable that could not be resolved.
+    fn expand_paths(&mut self) -> Result<(), String> {
+        for (field, value) in [
+            ("mkvtoolnix", &mut self.mkvtoolnix),
+            ("temp", &mut self.temp),
+            ("ffmpeg", &mut self.ffmpeg),
+            ("mediainfo", &mut self.mediainfo),
+            ("log", &mut self.log),
+        ] {
+            *value = utils::expand_path(value).map_err(|var| {
+                format!(
+                    "The environment variable '{var}' in the '{field}' path could not be resolved."
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Search for any tools whose paths were left blank via the PATH environment variable.
+    /// The resolved paths will be stored so that the search is only performed once.
+    ///
+    /// # Returns
+    ///
+    /// A result, which will contain a description of the first tool that could not be found.
+    fn resolve_tool_paths(&mut self) -> Result<(), String> {
+        let not_found = |field: &str, exe: &str| {
+            format!("The '{field}' path was not specified, and {exe} could not be found via the PATH environment variable.")
+        };
+
+        if self.mkvtoolnix.trim().is_empty() {
+            self.mkvtoolnix = utils::find_on_path("mkvmerge")
+                .and_then(|p| p.parent().map(|d| d.display().to_string()))
+                .ok_or_else(|| not_found("mkvtoolnix", "mkvmerge"))?;
+        }
+
+        if self.ffmpeg.trim().is_empty() {
+            self.ffmpeg = utils::find_on_path("ffmpeg")
+                .map(|p| p.display().to_string())
+                .ok_or_else(|| not_found("ffmpeg", "ffmpeg"))?;
+        }
+
+        if self.mediainfo.trim().is_empty() {
+            self.mediainfo = utils::find_on_path("mediainfo")
+                .map(|p| p.display().to_string())
+                .ok_or_else(|| not_found("mediainfo", "mediainfo"))?;
+        }
+
+        Ok(())
     }
 
     pub fn check_paths(&self) -> bool {
@@ -0,0 +1,146 @@
+use crate::logger;
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// The width of the progress bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// A progress indicator for a batch of media files, shown below the console output.
+pub struct Progress {
+    /// The total number of media files in the batch.
+    total: usize,
+    /// The number of media files that have been processed.
+    completed: AtomicUsize,
+    /// The time at which the batch was started.
+    start: Instant,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            completed: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// Indicate that a media file is being processed.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The name of the media file currently being processed.
+    pub fn set_current(&self, current: &str) {
+        self.draw(self.completed.load(Ordering::SeqCst), current);
+    }
+
+    /// Indicate that a media file has been processed.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The name of the media file that has been processed.
+    pub fn complete_one(&self, current: &str) {
+        let completed = self.completed.fetch_add(1, Ordering::SeqCst) + 1;
+        self.draw(completed, current);
+    }
+
+    /// Remove the progress indicator.
+    pub fn finish(&self) {
+        logger::set_progress(None);
+    }
+
+    /// Draw the progress indicator.
+    ///
+    /// # Arguments
+    ///
+    /// * `completed` - The number of media files that have been processed.
+    /// * `current` - The name of the media file currently being processed.
+    fn draw(&self, completed: usize, current: &str) {
+        logger::set_progress(Some(self.format_line(
+            completed,
+            current,
+            self.start.elapsed(),
+        )));
+    }
+
+    /// Format the progress indicator.
+    ///
+    /// # Arguments
+    ///
+    /// * `completed` - The number of media files that have been processed.
+    /// * `current` - The name of the media file currently being processed.
+    /// * `elapsed` - The time elapsed since the batch was started.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the progress indicator.
+    fn format_line(&self, completed: usize, current: &str, elapsed: Duration) -> String {
+        let total = self.total.max(1);
+        let filled = BAR_WIDTH * completed.min(total) / total;
+
+        // The estimate is based on the average time taken by the media files processed so far.
+        // The average must not be truncated, or any batch averaging under a second per file would
+        // never show a remaining time.
+        let eta = if completed > 0 {
+            let remaining = (total - completed.min(total)) as f64;
+            Progress::format_time(
+                (elapsed.as_secs_f64() * remaining / completed as f64).round() as u64,
+            )
+        } else {
+            "--:--:--".to_string()
+        };
+
+        format!(
+            "[{}{}] {completed} of {} | {current} | elapsed {} | ETA {eta}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            self.total,
+            Progress::format_time(elapsed.as_secs())
+        )
+    }
+
+    /// Format a number of seconds in the HH:MM:SS format.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The number of seconds.
+    fn format_time(seconds: u64) -> String {
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds % 3600) / 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the estimated time remaining from a progress indicator.
+    fn eta(total: usize, completed: usize, elapsed_ms: u64) -> String {
+        let line =
+            Progress::new(total).format_line(completed, "Title", Duration::from_millis(elapsed_ms));
+        line.rsplit("ETA ").next().unwrap().to_string()
+    }
+
+    #[test]
+    fn eta_uses_the_untruncated_average() {
+        assert_eq!(eta(1000, 60, 100_000), "00:26:07");
+        assert_eq!(eta(1000, 60, 30_000), "00:07:50");
+        assert_eq!(eta(100, 50, 500), "00:00:01");
+    }
+
+    #[test]
+    fn eta_is_unknown_until_a_file_completes() {
+        assert_eq!(eta(10, 0, 5_000), "--:--:--");
+    }
+
+    #[test]
+    fn eta_is_zero_once_the_batch_is_complete() {
+        assert_eq!(eta(10, 10, 5_000), "00:00:00");
+    }
+}
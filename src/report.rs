@@ -0,0 +1,112 @@
+use crate::{
+    logger,
+    media_file::{MediaFile, ProcessError},
+};
+
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, fs};
+
+#[derive(Serialize)]
+pub struct FileReport {
+    /// The path to the input media file.
+    pub input_path: String,
+    /// The path to the output media file.
+    pub output_path: String,
+    /// Was the media file successfully processed?
+    pub success: bool,
+    /// The reason that the media file could not be processed, if applicable.
+    pub error: Option<String>,
+    /// The time taken to process the media file, in seconds.
+    pub elapsed_seconds: u64,
+    /// The number of tracks of each type that were kept.
+    pub tracks_kept: BTreeMap<String, usize>,
+    /// The names of the attachments that were kept from the original file.
+    pub attachments_kept: Vec<String>,
+    /// The tracks that were kept, along with the outcome of any conversions.
+    pub tracks: Vec<TrackReport>,
+    /// Any warnings issued by MkvToolNix while processing the media file.
+    pub warnings: Vec<String>,
+}
+
+impl FileReport {
+    /// Create a [`FileReport`] from a processed media file.
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - The [`MediaFile`] that was processed.
+    /// * `output_path` - The path to the output media file.
+    /// * `error` - The [`ProcessError`] that occurred while processing the media file, if any.
+    /// * `elapsed_seconds` - The time taken to process the media file, in seconds.
+    pub fn new(
+        media: &MediaFile,
+        output_path: &str,
+        error: Option<&ProcessError>,
+        elapsed_seconds: u64,
+    ) -> Self {
+        let mut tracks_kept = BTreeMap::new();
+        for track in &media.media.tracks {
+            *tracks_kept.entry(track.track_type.to_string()).or_default() += 1;
+        }
+
+        Self {
+            input_path: media.file_path.clone(),
+            output_path: output_path.to_string(),
+            success: error.is_none(),
+            error: error.map(|e| e.to_string()),
+            elapsed_seconds,
+            tracks_kept,
+            attachments_kept: media.attachments.clone(),
+            tracks: media
+                .media
+                .tracks
+                .iter()
+                .map(|t| TrackReport {
+                    id: t.id,
+                    track_type: t.track_type.to_string(),
+                    language: t.language.clone(),
+                    codec: format!("{:?}", t.codec),
+                    converted: t.converted,
+                })
+                .collect(),
+            warnings: media.get_warnings(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TrackReport {
+    /// The index of the track within the original media file.
+    pub id: u32,
+    /// The type of the track.
+    pub track_type: String,
+    /// The language ID of the track.
+    pub language: String,
+    /// The codec of the track, after any conversions.
+    pub codec: String,
+    /// Was the track converted?
+    pub converted: bool,
+}
+
+/// Write the batch report to a JSON file.
+///
+/// # Arguments
+///
+/// * `path` - The path to the report file.
+/// * `reports` - The list of [`FileReport`] instances to be written.
+pub fn write_report(path: &str, reports: &[FileReport]) {
+    let json = match serde_json::to_string_pretty(reports) {
+        Ok(j) => j,
+        Err(e) => {
+            logger::log(format!("Failed to serialize the batch report: {e}"), true);
+            return;
+        }
+    };
+
+    match fs::write(path, json) {
+        Ok(_) => logger::log(format!("The batch report was written to '{path}'."), true),
+        Err(e) => logger::log(
+            format!("Failed to write the batch report to '{path}': {e}"),
+            true,
+        ),
+    }
+}
@@ -1,26 +1,88 @@
+use crate::logger;
+
 use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use titlecase::titlecase;
+use unicode_normalization::UnicodeNormalization;
 
 const BAD_NTFS_CHARS: [char; 9] = ['/', '?', '<', '>', '\\', ':', '*', '|', '"'];
 
+/// The order in which the substitution steps are applied, unless otherwise specified by the profile.
+const DEFAULT_ORDER: [SubstitutionStep; 5] = [
+    SubstitutionStep::TitleCase,
+    SubstitutionStep::RegularExpressions,
+    SubstitutionStep::Strings,
+    SubstitutionStep::StripInvalidChars,
+    SubstitutionStep::FixCaseAfterDashes,
+];
+
 lazy_static! {
     static ref UPPER_REGEX: Regex = Regex::new("(\\s[–-]\\s)(\\p{Ll})").unwrap();
+    static ref CAPTURE_REF_REGEX: Regex =
+        Regex::new(r"\$\$|\$\{([^}]*)\}|\$([_0-9A-Za-z]+)").unwrap();
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub enum NormalizationForm {
+    /// Canonical decomposition, followed by canonical composition.
+    #[serde(rename = "nfc")]
+    Nfc,
+    /// Canonical decomposition.
+    #[serde(rename = "nfd")]
+    Nfd,
+    /// Compatibility decomposition, followed by canonical composition.
+    #[serde(rename = "nfkc")]
+    Nfkc,
+    /// Compatibility decomposition.
+    #[serde(rename = "nfkd")]
+    Nfkd,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub enum SubstitutionStep {
+    /// Convert the string into title case.
+    #[serde(rename = "title_case")]
+    TitleCase,
+    /// Apply the regex substitutions.
+    #[serde(rename = "regular_expressions")]
+    RegularExpressions,
+    /// Apply the string substitutions.
+    #[serde(rename = "strings")]
+    Strings,
+    /// Strip any invalid characters.
+    #[serde(rename = "strip_invalid_chars")]
+    StripInvalidChars,
+    /// Fix the case of any words following a dash.
+    #[serde(rename = "fix_case_after_dashes")]
+    FixCaseAfterDashes,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Substitutions {
+    /// The Unicode normalization form to be applied before any other substitutions.
+    /// If unspecified, no normalization will be applied.
+    pub normalize_unicode: Option<NormalizationForm>,
+
     /// This will indicate whether we should convert titles into correct title case.
     /// If unspecified the value will default to true.
     #[serde(default = "default_title_case")]
     pub convert_to_proper_title_case: bool,
 
+    /// A list of words, such as acronyms and brand names, whose casing should be restored after
+    /// the title case conversion. Words are matched case-insensitively, and only as whole words.
+    #[serde(default)]
+    pub title_case_exceptions: Vec<String>,
+
     /// A list of regex substitutions to be applied when sanitizing a string.
+    /// Every match of the pattern will be replaced. The replacement may refer to capture groups
+    /// by number or by name, such as `$1` or `${name}`, and a literal `$` must be written as `$$`.
+    /// As `$1x` would refer to a group named `1x`, the braced form should be used when a reference
+    /// is followed by a letter, digit or underscore, such as `${1}x`.
     pub regular_expressions: Vec<[String; 2]>,
 
-    /// A list of regex substitutions to be applied when sanitizing a string.
+    /// A list of string substitutions to be applied when sanitizing a string.
     pub strings: Vec<[String; 2]>,
 
     /// This will indicate whether we should strip invalid NTFS characters from the string.
@@ -28,13 +90,31 @@ pub struct Substitutions {
     #[serde(default = "default_strip_ntfs")]
     pub strip_invalid_ntfs_chars: bool,
 
+    /// The list of characters to be stripped when stripping invalid characters from the string.
+    /// If unspecified the value will default to the characters that are invalid in NTFS file names.
+    #[serde(default = "default_invalid_chars")]
+    pub invalid_chars: Vec<char>,
+
     /// This will fix proper case after dashes when sanitizing a string.
     #[serde(default = "default_fix_dashes")]
     pub fix_case_after_dashes: bool,
 
+    /// The order in which the substitution steps should be applied.
+    /// If unspecified, the steps will be applied in the order in which they are listed above.
+    pub order: Option<Vec<SubstitutionStep>>,
+
     /// This will be lazily initialized upon first use.
     #[serde(skip)]
     regex_internal: Vec<(Regex, String)>,
+
+    /// The title case exception regular expressions, along with their canonical casing.
+    /// This will be lazily initialized upon first use.
+    #[serde(skip)]
+    exceptions_internal: Vec<(Regex, String)>,
+
+    /// Have the regular expressions been initialized?
+    #[serde(skip)]
+    initialized: bool,
 }
 
 impl Substitutions {
@@ -48,8 +128,8 @@ impl Substitutions {
     ///
     /// A string with any specified substitutions applied.
     pub fn apply(&mut self, in_string: &str) -> String {
-        if self.regex_internal.is_empty() {
-            assert!(self.initialize_regex());
+        if !self.initialized {
+            self.initialize_regex();
         }
 
         // Ensure that there are no stray tabs and spaces at the start
@@ -61,62 +141,194 @@ impl Substitutions {
             return String::new();
         }
 
-        // This should be the last action to be performed.
-        if self.convert_to_proper_title_case {
-            line = titlecase(&line);
-        }
+        // Normalize the string first, so that the substitutions match consistently.
+        line = match self.normalize_unicode {
+            Some(NormalizationForm::Nfc) => line.nfc().collect(),
+            Some(NormalizationForm::Nfd) => line.nfd().collect(),
+            Some(NormalizationForm::Nfkc) => line.nfkc().collect(),
+            Some(NormalizationForm::Nfkd) => line.nfkd().collect(),
+            None => line,
+        };
 
-        for (re, sub) in &self.regex_internal {
-            line = re.replace(&line, sub).to_string();
+        let order = self.order.as_deref().unwrap_or(&DEFAULT_ORDER);
+        for step in order {
+            line = self.apply_step(*step, line);
         }
 
-        for entry in &self.strings {
-            line = line.replace(&entry[0], &entry[1]);
-        }
+        line
+    }
 
-        if self.strip_invalid_ntfs_chars {
-            line = line.replace(&BAD_NTFS_CHARS[..], "");
-        }
+    /// Apply a single substitution step, if it is enabled by the substitution profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The [`SubstitutionStep`] to be applied.
+    /// * `line` - The input string.
+    ///
+    /// # Returns
+    ///
+    /// A string with the substitution step applied.
+    fn apply_step(&self, step: SubstitutionStep, mut line: String) -> String {
+        match step {
+            SubstitutionStep::TitleCase => {
+                if self.convert_to_proper_title_case {
+                    line = titlecase(&line);
+                    line = self.restore_title_case_exceptions(&line);
+                }
+            }
+            SubstitutionStep::RegularExpressions => {
+                for (re, sub) in &self.regex_internal {
+                    line = re.replace_all(&line, sub).to_string();
+                }
+            }
+            SubstitutionStep::Strings => {
+                for entry in &self.strings {
+                    line = line.replace(&entry[0], &entry[1]);
+                }
+            }
+            SubstitutionStep::StripInvalidChars => {
+                if self.strip_invalid_ntfs_chars {
+                    line = line.replace(&self.invalid_chars[..], "");
+                }
+            }
+            SubstitutionStep::FixCaseAfterDashes => {
+                if self.fix_case_after_dashes && line.contains('–') {
+                    let mut replacements = HashMap::new();
 
-        if self.fix_case_after_dashes && line.contains('–') {
-            let mut replacements = HashMap::new();
+                    for cap in UPPER_REGEX.captures_iter(&line) {
+                        let entire_match = cap.get(0).unwrap().as_str();
+                        let first_group = cap.get(1).unwrap().as_str();
+                        let second_group = cap.get(2).unwrap().as_str().to_uppercase();
 
-            for cap in UPPER_REGEX.captures_iter(&line) {
-                let entire_match = cap.get(0).unwrap().as_str();
-                let first_group = cap.get(1).unwrap().as_str();
-                let second_group = cap.get(2).unwrap().as_str().to_uppercase();
+                        replacements.insert(
+                            entire_match.to_string(),
+                            format!("{first_group}{second_group}"),
+                        );
+                    }
 
-                replacements.insert(
-                    entire_match.to_string(),
-                    format!("{first_group}{second_group}"),
-                );
+                    for (input, output) in replacements {
+                        line = line.replace(&input, &output);
+                    }
+                }
             }
+        }
+
+        line
+    }
 
-            for (input, output) in replacements {
-                line = line.replace(&input, &output);
+    /// Restore the canonical casing of any title case exceptions within a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The input string.
+    ///
+    /// # Returns
+    ///
+    /// A string with the casing of any exceptions restored.
+    fn restore_title_case_exceptions(&self, line: &str) -> String {
+        let mut line = line.to_string();
+        for (re, exception) in &self.exceptions_internal {
+            let mut out = String::with_capacity(line.len());
+            let mut last = 0;
+            for m in re.find_iter(&line) {
+                // Only whole words should be matched, so that "ios" doesn't alter "Radios".
+                let before = line[..m.start()].chars().next_back();
+                let after = line[m.end()..].chars().next();
+                if before.is_some_and(char::is_alphanumeric)
+                    || after.is_some_and(char::is_alphanumeric)
+                {
+                    continue;
+                }
+
+                out.push_str(&line[last..m.start()]);
+                out.push_str(exception);
+                last = m.end();
             }
+            out.push_str(&line[last..]);
+            line = out;
         }
 
         line
     }
 
-    /// Initialize any regular expression objects as specified by the substitution profile.
+    /// Find any capture groups referred to by a replacement string that don't exist within a regular expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `re` - The regular expression.
+    /// * `replacement` - The replacement string.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing the names of any missing capture groups.
+    fn missing_capture_groups(re: &Regex, replacement: &str) -> Vec<String> {
+        CAPTURE_REF_REGEX
+            .captures_iter(replacement)
+            .filter_map(|cap| cap.get(1).or(cap.get(2)))
+            .map(|name| name.as_str())
+            .filter(|name| match name.parse::<usize>() {
+                Ok(index) => index >= re.captures_len(),
+                Err(_) => !re.capture_names().flatten().any(|n| n == *name),
+            })
+            .map(String::from)
+            .collect()
+    }
+
+    /// Check whether the regular expression substitutions are valid.
     ///
     /// # Returns
     ///
-    /// True if the regular expressions were successfully initialized, false otherwise.
-    fn initialize_regex(&mut self) -> bool {
+    /// True if every regular expression is valid and only refers to capture groups that exist, false otherwise.
+    pub fn is_valid(&self) -> bool {
+        let mut valid = true;
+        for entry in &self.regular_expressions {
+            match Regex::new(&entry[0]) {
+                Ok(re) => {
+                    // A reference to a missing capture group would silently be replaced with nothing.
+                    let missing = Substitutions::missing_capture_groups(&re, &entry[1]);
+                    if !missing.is_empty() {
+                        logger::log(
+                            format!(
+                                "The replacement '{}' for regex '{}' refers to capture groups that don't exist: {}",
+                                entry[1],
+                                entry[0],
+                                missing.join(", ")
+                            ),
+                            true,
+                        );
+                        valid = false;
+                    }
+                }
+                Err(e) => {
+                    logger::log(
+                        format!("The substitution regex '{}' is invalid: {e}", entry[0]),
+                        true,
+                    );
+                    valid = false;
+                }
+            }
+        }
+
+        valid
+    }
+
+    /// Initialize any regular expression objects as specified by the substitution profile.
+    /// Any invalid regular expressions will have been reported by [`Substitutions::is_valid`], and are skipped here.
+    fn initialize_regex(&mut self) {
         for entry in &self.regular_expressions {
-            let r = Regex::new(&entry[0]);
-            if let Ok(re) = r {
+            if let Ok(re) = Regex::new(&entry[0]) {
                 self.regex_internal.push((re, entry[1].clone()));
-            } else {
-                eprintln!("An error occurred while initializing regex: {r:?}");
-                return false;
             }
         }
 
-        true
+        // The exceptions are escaped, and so will always form a valid regular expression.
+        for exception in &self.title_case_exceptions {
+            if let Ok(re) = Regex::new(&format!("(?i){}", regex::escape(exception))) {
+                self.exceptions_internal.push((re, exception.clone()));
+            }
+        }
+
+        self.initialized = true;
     }
 }
 
@@ -124,6 +336,10 @@ fn default_fix_dashes() -> bool {
     true
 }
 
+fn default_invalid_chars() -> Vec<char> {
+    BAD_NTFS_CHARS.to_vec()
+}
+
 fn default_strip_ntfs() -> bool {
     true
 }
@@ -131,3 +347,73 @@ fn default_strip_ntfs() -> bool {
 fn default_title_case() -> bool {
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a set of substitutions containing a single regular expression substitution.
+    ///
+    /// # Arguments
+    ///
+    /// * `regex` - The regular expression.
+    /// * `replacement` - The replacement string.
+    fn regex_substitution(regex: &str, replacement: &str) -> Substitutions {
+        serde_json::from_value(serde_json::json!({
+            "convert_to_proper_title_case": false,
+            "regular_expressions": [[regex, replacement]],
+            "strings": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn every_regex_match_is_replaced() {
+        let mut subs = regex_substitution("\\s*\\[[^]]*\\]", "");
+
+        assert_eq!(subs.apply("Title [1080p] Part [Remux]"), "Title Part");
+    }
+
+    #[test]
+    fn numbered_capture_groups_are_substituted() {
+        let mut subs = regex_substitution("S0*(\\d+)E(\\d+)", "${1}x$2");
+
+        assert!(subs.is_valid());
+        assert_eq!(subs.apply("Show S01E02"), "Show 1x02");
+    }
+
+    #[test]
+    fn named_capture_groups_are_substituted() {
+        let mut subs = regex_substitution(
+            "S0*(?P<season>\\d+)E(?P<episode>\\d+)",
+            "${season}x${episode}",
+        );
+
+        assert!(subs.is_valid());
+        assert_eq!(subs.apply("Show S01E02 and S01E03"), "Show 1x02 and 1x03");
+    }
+
+    #[test]
+    fn missing_capture_groups_are_invalid() {
+        for replacement in ["$2", "${3}x", "${name}"] {
+            let subs = regex_substitution("S(\\d+)", replacement);
+
+            assert!(!subs.is_valid(), "{replacement}");
+        }
+    }
+
+    #[test]
+    fn invalid_regex_is_invalid() {
+        let subs = regex_substitution("(unclosed", "");
+
+        assert!(!subs.is_valid());
+    }
+
+    #[test]
+    fn escaped_dollar_is_not_a_capture_group() {
+        let mut subs = regex_substitution("USD", "$$");
+
+        assert!(subs.is_valid());
+        assert_eq!(subs.apply("5 USD"), "5 $");
+    }
+}
@@ -1,8 +1,24 @@
+use crate::{
+    conversion_params::unified::MiscParams,
+    logger::{self, Level},
+};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
 use std::{
+    env,
     fmt::Display,
     path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
+lazy_static! {
+    /// Matches an environment variable reference, in the $VAR, ${VAR} or %VAR% formats.
+    static ref ENV_VAR_REGEX: Regex =
+        Regex::new(r"\$\{(\w+)\}|\$(\w+)|%(\w+)%").unwrap();
+}
+
 /// Convert a boolean value to yes or no.
 ///
 /// # Arguments
@@ -13,6 +29,59 @@ pub fn bool_to_yes_no(b: bool) -> String {
     (if b { "yes" } else { "no" }).to_string()
 }
 
+/// Expand a leading tilde and any environment variable references within a path.
+///
+/// # Arguments
+///
+/// * `path` - The path to be expanded.
+///
+/// # Returns
+///
+/// A result containing the expanded path, or the name of the first variable that could not be resolved.
+pub fn expand_path(path: &str) -> Result<String, String> {
+    let mut path = path.to_string();
+
+    // A leading tilde refers to the home directory of the current user.
+    if path == "~" || path.starts_with("~/") || path.starts_with("~\\") {
+        let home = env::var("HOME")
+            .or_else(|_| env::var("USERPROFILE"))
+            .map_err(|_| "HOME".to_string())?;
+        path.replace_range(..1, &home);
+    }
+
+    let mut missing = None;
+    let expanded = ENV_VAR_REGEX.replace_all(&path, |caps: &Captures| {
+        let name = (1..=3).find_map(|i| caps.get(i)).unwrap().as_str();
+        env::var(name).unwrap_or_else(|_| {
+            missing.get_or_insert_with(|| name.to_string());
+            String::new()
+        })
+    });
+
+    match missing {
+        Some(name) => Err(name),
+        None => Ok(expanded.to_string()),
+    }
+}
+
+/// Search the directories within the PATH environment variable for an executable.
+///
+/// # Arguments
+///
+/// * `name` - The name of the executable, without any platform-specific extension.
+///
+/// # Returns
+///
+/// The full path to the executable, if it was found.
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{name}{}", env::consts::EXE_SUFFIX);
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .map(|dir| dir.join(&file_name))
+        .find(|p| p.is_file())
+}
+
 /// Return a boolean value indicating whether a given directory exists.
 ///
 /// # Arguments
@@ -69,6 +138,29 @@ const DURATION_TERMS: [&str; 4] = ["day", "hour", "minute", "second"];
 /// The length of various durations in seconds.
 const DURATIONS: [u64; 4] = [SECONDS_IN_DAY, SECONDS_IN_HOUR, SECONDS_IN_MINUTE, 1];
 
+/// The textual terms for various supported file sizes.
+const SIZE_TERMS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// Convert a file size (in bytes) into a human readable string.
+///
+/// # Arguments
+///
+/// * `bytes` - The file size, in bytes.
+pub fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut term = 0;
+    while size >= 1024.0 && term < SIZE_TERMS.len() - 1 {
+        size /= 1024.0;
+        term += 1;
+    }
+
+    if term == 0 {
+        format!("{bytes} {}", SIZE_TERMS[0])
+    } else {
+        format!("{size:.2} {}", SIZE_TERMS[term])
+    }
+}
+
 /// Convert a duration (in seconds) into days, hours, minutes and seconds.
 ///
 /// # Arguments
@@ -109,6 +201,97 @@ pub fn format_duration(seconds: u64) -> String {
     formatted
 }
 
+/// Escape any characters within a string that have a special meaning in XML.
+///
+/// # Arguments
+///
+/// * `str` - The string to be escaped.
+pub fn escape_xml(str: &str) -> String {
+    let mut escaped = String::with_capacity(str.len());
+
+    for c in str.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Compute the 64-bit FNV-1a hash of a slice of bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to be hashed.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// Run a process, retrying it if it fails, as specified by the [`MiscParams`].
+/// A failure that yields the same error output twice is assumed to be deterministic, and will not be retried.
+///
+/// # Arguments
+///
+/// * `name` - The name of the process, used when logging.
+/// * `misc` - The [`MiscParams`] containing the retry options.
+/// * `is_failure` - A function that indicates whether an exit code represents a failure.
+/// * `run` - A function that runs the process, returning the exit code and error output.
+///
+/// # Returns
+///
+/// A tuple containing the exit code and error output of the final attempt.
+pub fn run_with_retries<F, R>(
+    name: &str,
+    misc: &MiscParams,
+    is_failure: F,
+    mut run: R,
+) -> (i32, String)
+where
+    F: Fn(i32) -> bool,
+    R: FnMut() -> (i32, String),
+{
+    let retries = misc.retry_count.unwrap_or(0);
+    let delay = Duration::from_secs(misc.retry_delay_secs.unwrap_or(0));
+
+    let mut attempt = 0;
+    let mut last_error: Option<String> = None;
+    loop {
+        let (code, error) = run();
+        if !is_failure(code) || attempt >= retries {
+            return (code, error);
+        }
+
+        if last_error.as_ref() == Some(&error) {
+            logger::log_level(
+                Level::Warn,
+                format!("{name} failed twice with the same output, it will not be retried."),
+            );
+            return (code, error);
+        }
+
+        attempt += 1;
+        logger::log_level(
+            Level::Warn,
+            format!(
+                "{name} failed with exit code {code}, retrying (attempt {attempt} of {retries})..."
+            ),
+        );
+
+        thread::sleep(delay);
+        last_error = Some(error);
+    }
+}
+
 /// Get the extension of a given file path.
 ///
 /// # Arguments
@@ -129,6 +312,40 @@ pub fn get_file_name(fp: &str) -> Option<String> {
     Some(Path::new(fp).file_name()?.to_str()?.to_string())
 }
 
+/// Get the MIME type associated with a given file extension.
+///
+/// # Arguments
+///
+/// * `ext` - The lowercase file extension, without the leading dot.
+///
+/// # Returns
+///
+/// The MIME type, if the extension is known.
+pub fn get_mime_type(ext: &str) -> Option<&'static str> {
+    let mime = match ext {
+        // Fonts.
+        "otf" => "font/otf",
+        "ttc" => "font/collection",
+        "ttf" => "font/ttf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        // Images.
+        "bmp" => "image/bmp",
+        "gif" => "image/gif",
+        "jpeg" | "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        // Text.
+        "json" => "application/json",
+        "nfo" | "txt" => "text/plain",
+        "pdf" => "application/pdf",
+        "xml" => "application/xml",
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
 /// Join several path segments into a single path.
 ///
 /// # Arguments